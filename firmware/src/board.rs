@@ -0,0 +1,240 @@
+use avr_device::atmega328p::{Peripherals, tc0, adc};
+
+use roller_core::display::position;
+use roller_core::hardware::{DisplayPort, Measurement, Sampler, TickRate, Timer};
+
+/// Performs initial hardware initialization.
+pub fn initialize() {
+    let p = unsafe {
+        Peripherals::steal()
+    };
+    // enable the default "idle" sleeping mode
+    p.CPU.smcr.write(|w| w.se().variant(true));
+
+    LedMatrix::initialize();
+    Timer0::initialize();
+    Adc::initialize();
+
+    #[cfg(feature = "debug_spi")]
+    debug_init();
+
+    #[cfg(feature = "console")]
+    Usart::initialize();
+}
+
+/// The 7-segment displays connected to the I/O ports "B" (displays) and "D" (segments).
+pub struct LedMatrix;
+
+impl LedMatrix {
+    /// Prepares I/O ports "B" and "D" for the operation of the display.
+    fn initialize() {
+        unsafe {
+            let p = Peripherals::steal();
+            // Switch all channels of the I/O port "D" into the output mode.
+            p.PORTD.ddrd.write(|w| w.bits(0xff));
+            // Set outputs of all the I/O port "D" channels to LOW - no segment is active.
+            p.PORTD.portd.write(|w| w.bits(0));
+
+            // Switch channels of the I/O port "B" connected to the displays into the output mode.
+            p.PORTB.ddrb.modify(|r, w| w.bits(r.bits() | position::MASK_ALL));
+            // Set outputs of the I/O port "B" channels connected to the displays to LOW — no display is selected.
+            p.PORTB.portb.modify(|r, w| w.bits(r.bits() & !position::MASK_ALL));
+        }
+    }
+
+}
+
+impl DisplayPort for LedMatrix {
+    fn output(&mut self, symbol: u8, position: u8) {
+        unsafe {
+            let p = Peripherals::steal();
+            // Turn off all segments on the currently active display.
+            p.PORTD.portd.write(|w| w.bits(0));
+            // Unset all channel bits connected to the displays, set the bits of the displays that must be activated.
+            p.PORTB.portb.modify(|r, w| w.bits((r.bits() & !position::MASK_ALL) | position));
+            // Copy the symbol into the I/O port "D".
+            p.PORTD.portd.write(|w| w.bits(symbol));
+        };
+    }
+
+    fn fail(&mut self, symbol: u8) {
+        unsafe {
+            let p = Peripherals::steal();
+            p.PORTD.ddrd.write(|w| w.bits(0xff));
+            p.PORTD.portd.write(|w| w.bits(symbol));
+            p.PORTB.ddrb.modify(|r, w| w.bits(r.bits() | position::MASK_ALL));
+            p.PORTB.portb.write(|w| w.bits(position::MASK_ALL));
+        }
+    }
+}
+
+/// The Analog-to-Digital Converter and the channels the accelerometer and the pots are connected to.
+pub struct Adc;
+
+impl Adc {
+    /// Initialize ADC.
+    fn initialize() {
+        let p = unsafe { Peripherals::steal() };
+        // clear the ADC power reduction bit of the power reduction register.
+        p.CPU.prr.modify(|_, w| w
+            .pradc().variant(false)
+        );
+        p.ADC.adcsra.write(|w| w
+            // set the ADC prescaler to /128 (puts the ADC clock into the required 50kHz-100kHz range).
+            .adps().variant(adc::adcsra::ADPS_A::PRESCALER_128)
+            // enable the ADC interrupt.
+            .adie().variant(true)
+        );
+    }
+
+    /// Returns the result of the latest conversion.
+    pub fn result() -> u16 {
+        let p = unsafe { Peripherals::steal() };
+        p.ADC.adc.read().bits()
+    }
+}
+
+impl Sampler for Adc {
+    /// Assumes the MCU frequency to be 1MHz.
+    fn start(&mut self, m: &Measurement) {
+        // maps measurements to the ADC channels connected to the corresponding devices on the board.
+        let chan = match m {
+            Measurement::AccX => adc::admux::MUX_A::ADC0,
+            Measurement::AccY => adc::admux::MUX_A::ADC1,
+            Measurement::AccZ => adc::admux::MUX_A::ADC2,
+            Measurement::PotQuantity => adc::admux::MUX_A::ADC3,
+            Measurement::PotQuality => adc::admux::MUX_A::ADC4,
+        };
+
+        let p = unsafe { Peripherals::steal() };
+        p.ADC.admux.write(|w| w
+            // specify the source channel for the ADC.
+            .mux().variant(chan)
+            // specify that the AVCC pin of the MCU must be used as a reference.
+            .refs().variant(adc::admux::REFS_A::AVCC)
+        );
+        p.ADC.adcsra.modify(|_, w| w
+            // enable the ADC.
+            .aden().variant(true)
+            // start an ADC conversion.
+            .adsc().variant(true)
+        );
+    }
+
+    fn stop(&mut self) {
+        let p = unsafe { Peripherals::steal() };
+        // disable the ADC
+        p.ADC.adcsra.modify(|_, w| w.aden().variant(false));
+    }
+}
+
+/// The hardware timer0 that calls the `TIMER0_COMPA` interrupt handler.
+pub struct Timer0;
+
+impl Timer0 {
+    /// Initializes the hardware timer to call the interrupt handler at approximately f=200Hz
+    ///
+    /// Assumes the MCU frequency to be 8MHz.
+    fn initialize() {
+        let p = unsafe { Peripherals::steal() };
+
+        // enables "Clear Timer on Compare" mode for timer0.
+        p.TC0.tccr0a.write(|w| w.wgm0().variant(tc0::tccr0a::WGM0_A::CTC));
+        Timer0.set_rate(TickRate::Normal);
+        // enables Output Compare Match "A" Interrupt for timer0.
+        p.TC0.timsk0.write(|w| w.ocie0a().bit(true));
+
+        // TODO: calculate the best prescaler and OCR values for the desired freqnency with a macro
+    }
+}
+
+impl Timer for Timer0 {
+    fn set_rate(&mut self, rate: TickRate) {
+        let p = unsafe { Peripherals::steal() };
+        // sets prescaler to /1024 for timer0.
+        p.TC0.tccr0b.write(|w| w.cs0().variant(tc0::tccr0b::CS0_A::PRESCALE_1024));
+        match rate {
+            // sets timer0's Output Compare Register "A" to 38 ((8,000,000/1024)/(38+1)) = 200.3205)
+            TickRate::Normal => p.TC0.ocr0a.write(|w| w.bits(38)),
+            // sets timer0's Output Compare Register "A" to 155 ((8,000,000/1024)/(155+1)) = 50.0801)
+            TickRate::Sleeping => p.TC0.ocr0a.write(|w| w.bits(155)),
+        }
+    }
+}
+
+#[cfg(feature = "debug_spi")]
+fn debug_init() {
+    let p = unsafe { Peripherals::steal() };
+    p.CPU.prr.modify(|_, w| w
+        .prspi().variant(false)
+    );
+    p.PORTB.ddrb.modify(|_, w| w
+        .pb3().variant(true)
+        .pb5().variant(true)
+    );
+    p.SPI.spcr.write(|w| w
+        .spie().variant(true)
+        .spe().variant(true)
+        .mstr().variant(true)
+    )
+}
+
+/// Starts sending the provided byte over SPI.
+#[cfg(feature = "debug_spi")]
+pub fn debug_send(data: u8) {
+    let p = unsafe { Peripherals::steal() };
+    p.SPI.spdr.write(|w| w.bits(data));
+}
+
+/// The USART the serial console is connected to.
+///
+/// The USART takes over the RXD and TXD pins of the I/O port "D", so the segments A and B connected to them
+/// follow the serial lines rather than the display buffer while the console is enabled. The console is only
+/// built along with the "bench" feature, every setting the device needs to roll is picked in the settings menu
+/// instead.
+#[cfg(feature = "console")]
+pub struct Usart;
+
+#[cfg(feature = "console")]
+impl Usart {
+    /// Initializes the USART for 9600 baud, 8 data bits, no parity, 1 stop bit.
+    ///
+    /// Assumes the MCU frequency to be 8MHz.
+    fn initialize() {
+        let p = unsafe { Peripherals::steal() };
+        p.CPU.prr.modify(|_, w| w
+            .prusart0().variant(false)
+        );
+        // double the transmission speed to reduce the baud rate error.
+        p.USART0.ucsr0a.write(|w| w.u2x0().variant(true));
+        // (8,000,000/(8*(103+1)) = 9615.4 baud
+        p.USART0.ubrr0.write(|w| w.bits(103));
+        p.USART0.ucsr0b.write(|w| w
+            // enable the receive complete interrupt.
+            .rxcie0().variant(true)
+            .rxen0().variant(true)
+            .txen0().variant(true)
+        );
+        // 8 data bits, no parity and 1 stop bit are the defaults of UCSR0C.
+    }
+
+    /// Returns the received byte.
+    pub fn read() -> u8 {
+        let p = unsafe { Peripherals::steal() };
+        p.USART0.udr0.read().bits()
+    }
+
+    /// Starts sending the provided byte.
+    pub fn send(data: u8) {
+        unsafe {
+            let p = Peripherals::steal();
+            p.USART0.udr0.write(|w| w.bits(data));
+        }
+    }
+
+    /// Enables or disables the "data register empty" interrupt that requests the next byte to be sent.
+    pub fn set_sending(sending: bool) {
+        let p = unsafe { Peripherals::steal() };
+        p.USART0.ucsr0b.modify(|_, w| w.udrie0().variant(sending));
+    }
+}
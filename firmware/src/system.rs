@@ -1,5 +1,7 @@
-use crate::display::fail_with_symbol;
-use crate::display::symbol::{ALPHA, ECHO};
+use roller_core::display::symbol::{ALPHA, ECHO};
+use roller_core::hardware::DisplayPort;
+
+use crate::board::LedMatrix;
 
 #[panic_handler]
 #[no_mangle]
@@ -8,7 +10,7 @@ use crate::display::symbol::{ALPHA, ECHO};
 unsafe extern "C" fn panic(_panic: &core::panic::PanicInfo<'_>) -> ! {
     // TODO: display an actual error code instead of "EEEE" if available from PanicInfo.
     avr_device::interrupt::disable();
-    fail_with_symbol(ECHO);
+    LedMatrix.fail(ECHO);
     loop {}
 }
 
@@ -17,5 +19,5 @@ unsafe extern "C" fn panic(_panic: &core::panic::PanicInfo<'_>) -> ! {
 /// Currently shows "AAAA" on the display.
 unsafe extern "C" fn abort() {
     avr_device::interrupt::disable();
-    fail_with_symbol(ALPHA);
+    LedMatrix.fail(ALPHA);
 }
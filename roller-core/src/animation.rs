@@ -0,0 +1,241 @@
+use crate::display::segment::*;
+use crate::display::position;
+use crate::display::{Buffer, Display};
+use crate::hardware::DisplayPort;
+use crate::device::MAX_DICE;
+
+/// Implements the rolling animation: single segment spinning around 4 digit displays.
+pub struct Spinner {
+    next_frame: usize,
+    ticks_left: u8,
+}
+
+impl Spinner {
+    const EXPECTED_FREQUENCY_HZ: u8 = 200;
+    const TICKS_PER_FRAME: u8 = Self::EXPECTED_FREQUENCY_HZ / 25;
+    const FRAMES: [Buffer; 12] = [
+        [A, 0, 0, 0],
+        [0, A, 0, 0],
+        [0, 0, A, 0],
+        [0, 0, 0, A],
+        [0, 0, 0, B],
+        [0, 0, 0, C],
+        [0, 0, 0, D],
+        [0, 0, D, 0],
+        [0, D, 0, 0],
+        [D, 0, 0, 0],
+        [E, 0, 0, 0],
+        [F, 0, 0, 0],
+    ];
+
+    /// Returns a new instance of Spinner.
+    pub fn new() -> Self {
+        Self {
+            next_frame: 0,
+            ticks_left: 0,
+        }
+    }
+
+    /// Updates the animation's internal state and maybe updates the provided writable display buffer.
+    ///
+    /// This is intended to be called at EXPECTED_FREQUENCY_HZ by the timer interrupt handler.
+    pub fn advance(&mut self, buffer: &mut Buffer) {
+        if self.ticks_left > 0 {
+            self.ticks_left -= 1;
+            return;
+        }
+
+        buffer.copy_from_slice(&Self::FRAMES[self.next_frame]);
+
+        self.next_frame = (self.next_frame + 1) % Self::FRAMES.len();
+        self.ticks_left = Self::TICKS_PER_FRAME - 1;
+    }
+}
+
+impl Default for Spinner {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Implements the animation shown before the result of a critical roll: all the displays flash for
+/// the maximum face, the segments fall from the top to the bottom for the minimum one. Leaves the result
+/// on the display once it's over.
+pub struct Critical {
+    frames: &'static [Buffer],
+    result: Buffer,
+    next_frame: usize,
+    ticks_left: u8,
+}
+
+impl Critical {
+    const EXPECTED_FREQUENCY_HZ: u8 = 200;
+    const TICKS_PER_FRAME: u8 = Self::EXPECTED_FREQUENCY_HZ / 10;
+    const ALL: u8 = A | B | C | D | E | F | G | POINT;
+    const SUCCESS: [Buffer; 6] = [
+        [Self::ALL; 4],
+        [0; 4],
+        [Self::ALL; 4],
+        [0; 4],
+        [Self::ALL; 4],
+        [0; 4],
+    ];
+    const FAILURE: [Buffer; 8] = [
+        [A; 4],
+        [G; 4],
+        [D; 4],
+        [0; 4],
+        [A; 4],
+        [G; 4],
+        [D; 4],
+        [0; 4],
+    ];
+
+    /// Returns a new instance of Critical for the maximum (`success`) or the minimum face, followed by the result.
+    pub fn new(success: bool, result: Buffer) -> Self {
+        Self {
+            frames: if success { &Self::SUCCESS } else { &Self::FAILURE },
+            result,
+            next_frame: 0,
+            ticks_left: 0,
+        }
+    }
+
+    /// Updates the animation's internal state and maybe updates the provided writable display buffer.
+    /// Returns true once the animation is over and the result is on the display.
+    ///
+    /// This is intended to be called at EXPECTED_FREQUENCY_HZ by the timer interrupt handler.
+    pub fn advance(&mut self, buffer: &mut Buffer) -> bool {
+        if self.ticks_left > 0 {
+            self.ticks_left -= 1;
+            return false;
+        }
+
+        match self.frames.get(self.next_frame) {
+            Some(frame) => buffer.copy_from_slice(frame),
+            None => {
+                buffer.copy_from_slice(&self.result);
+                return true;
+            }
+        }
+
+        self.next_frame += 1;
+        self.ticks_left = Self::TICKS_PER_FRAME - 1;
+        false
+    }
+}
+
+/// Implements a slideshow: a few display buffers shown one after another, each one for its own
+/// number of ticks. Starts over after the last slide, unless a slide is shown for 0 ticks: such a slide
+/// stays on the display and ends the slideshow. Doesn't touch the display buffer if empty.
+pub struct Slideshow {
+    slides: [(Buffer, u16); Self::MAX_SLIDES],
+    count: usize,
+    next_slide: usize,
+    ticks_left: u16,
+}
+
+impl Slideshow {
+    /// Enough for every die of a roll between two slides of the total, even if every die needs a slide
+    /// for its index too.
+    pub const MAX_SLIDES: usize = 2 * MAX_DICE + 2;
+
+    /// Returns a new empty instance of Slideshow.
+    pub const fn new() -> Self {
+        Self {
+            slides: [([0; 4], 0); Self::MAX_SLIDES],
+            count: 0,
+            next_slide: 0,
+            ticks_left: 0,
+        }
+    }
+
+    /// Appends a slide shown for the specified number of ticks, 0 meaning forever.
+    /// Slides beyond `MAX_SLIDES` are ignored.
+    pub fn push(&mut self, buffer: Buffer, ticks: u16) {
+        if self.count < Self::MAX_SLIDES {
+            self.slides[self.count] = (buffer, ticks);
+            self.count += 1;
+        }
+    }
+
+    /// Returns whether there are no slides.
+    pub fn is_empty(&self) -> bool {
+        self.count == 0
+    }
+
+    /// Updates the animation's internal state and maybe updates the provided writable display buffer.
+    ///
+    /// This is intended to be called by the timer interrupt handler.
+    pub fn advance(&mut self, buffer: &mut Buffer) {
+        if self.count == 0 {
+            return;
+        }
+        if self.ticks_left > 0 {
+            self.ticks_left -= 1;
+            return;
+        }
+
+        let (slide, ticks) = self.slides[self.next_slide];
+        buffer.copy_from_slice(&slide);
+        if ticks == 0 {
+            self.count = 0;
+            return;
+        }
+
+        self.next_slide = (self.next_slide + 1) % self.count;
+        self.ticks_left = ticks.saturating_sub(1);
+    }
+}
+
+impl Default for Slideshow {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Implements the sleeping animation: single rightmost dot appears for a moment every few seconds
+pub struct BlinkingDot {
+    dot_visible: bool,
+    ticks_left: u16,
+}
+
+impl BlinkingDot {
+    const EXPECTED_FREQUENCY_HZ: u8 = 50;
+    const TICKS_VISIBLE: u16 = (Self::EXPECTED_FREQUENCY_HZ / 2) as u16;
+    const TICKS_HIDDEN: u16 = Self::EXPECTED_FREQUENCY_HZ as u16 * 10;
+
+    /// Returns a new instance of BlinkingDot
+    pub fn new() -> Self {
+        Self {
+            dot_visible: false,
+            ticks_left: Self::TICKS_HIDDEN - 1,
+        }
+    }
+
+    /// Updates the animation's internal state and maybe updates the provided display.
+    ///
+    /// This is intended to be called at EXPECTED_FREQUENCY_HZ by the timer interrupt handler.
+    pub fn advance<P: DisplayPort>(&mut self, display: &mut Display, port: &mut P) {
+        if self.ticks_left > 0 {
+            self.ticks_left -= 1;
+            return;
+        }
+
+        if self.dot_visible {
+            self.dot_visible = false;
+            self.ticks_left = Self::TICKS_HIDDEN - 1;
+            display.force_output(port, 0, 0);
+        } else {
+            self.dot_visible = true;
+            self.ticks_left = Self::TICKS_VISIBLE - 1;
+            display.force_output(port, POINT, position::D4);
+        }
+    }
+}
+
+impl Default for BlinkingDot {
+    fn default() -> Self {
+        Self::new()
+    }
+}
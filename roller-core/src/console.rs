@@ -0,0 +1,464 @@
+//! Line-based command shell of the serial console.
+//!
+//! Commands are ASCII words separated by spaces, one command per line:
+//!
+//! | Command                  | Reply                                                           |
+//! |--------------------------|-----------------------------------------------------------------|
+//! | `help`                   | the list of commands                                            |
+//! | `state`                  | the current device state                                        |
+//! | `settings`               | the active settings, e.g. `3d6`, `1d20+5`, `4d6kh3`, `2d6!`,    |
+//! |                          | `10d10>=7f1`, `2d6ro<2` or `2d6+1d8`                            |
+//! | `last`                   | the total of the last roll, its dice and the modifier, dropped  |
+//! |                          | dice are put in parentheses, exploded ones are followed by `!`, |
+//! |                          | Fudge dice are shown as `-`, `0` and `+`, the subtotals of      |
+//! |                          | mixed dice follow, e.g. `(2d6: 7, 1d8: 5)`                      |
+//! | `adc`                    | the latest raw measurements of the pots and the accelerometer   |
+//! | `entropy`                | how many whitened bits are queued for the dice, and how many    |
+//! |                          | have been harvested and consumed since the start, e.g.          |
+//! |                          | `queued 40 harvested 1000 consumed 960`                         |
+//! | `roll`                   | starts a roll with the active settings                          |
+//! | `set NdM`                | overrides the settings selected by the knobs, N can be `H` or   |
+//! |                          | `L` for advantage and disadvantage, e.g. `Hd20`, M can be `F`   |
+//! |                          | for Fudge dice                                                  |
+//! | `set pots`               | gives control over the settings back to the knobs               |
+//! | `preset EXPR`            | overrides all the settings with an expression in the dice       |
+//! |                          | notation, e.g. `4d6kh3` or `2d6+1d8+3`, see [`crate::notation`] |
+//! | `sides M`                | changes the side count selected by the last zone of the quality |
+//! |                          | knob, `set NdM` does it too for side counts missing on the knob |
+//! | `pool NdM`               | adds a group of dice rolled along with the selected ones        |
+//! | `pool clear`             | rolls the selected dice on their own                            |
+//! | `modifier K`             | changes the modifier added to every roll, e.g. `+5` or `-1`     |
+//! | `keep hK`, `keep lK`     | keeps K highest or lowest dice of every roll                    |
+//! | `keep all`               | adds all the dice of every roll together                        |
+//! | `count T`                | counts the dice of every roll that meet the target T instead of |
+//! |                          | adding them together                                            |
+//! | `count T botch`          | also makes every 1 cancel a success                             |
+//! | `count off`              | adds all the dice of every roll together                        |
+//! | `reroll F`               | rerolls throws of face F or lower once                          |
+//! | `reroll F always`        | rerolls throws of face F or lower until a higher face comes up  |
+//! | `reroll off`             | keeps every throw                                               |
+//! | `dropped on\|off`       | enables or disables showing the dropped dice after a roll       |
+//! | `dice on\|off`          | enables or disables showing every die after a roll              |
+//! | `crit on\|off`          | enables or disables animating rolls of a single die that show   |
+//! |                          | its maximum or minimum face                                     |
+//! | `explode on\|off`       | enables or disables throwing the dice that show their maximum   |
+//! |                          | face again                                                      |
+//! | `threshold`              | the roll detection thresholds                                   |
+//! | `threshold NAME VALUE`   | changes a roll detection threshold                              |
+//!
+//! Every reply ends with CR LF. Failed commands are answered with `error: ` followed by the reason.
+
+use core::str;
+
+use crate::device::{Group, Mode, Reroll, Thresholds, MAX_DICE, MAX_GROUPS, MAX_SIDES};
+use crate::notation::{self, Program};
+use crate::scales::{ADVANTAGE, DISADVANTAGE, FUDGE};
+use crate::utils::Ring;
+
+/// The longest line accepted by the console, not counting the line terminator.
+pub const MAX_LINE: usize = 32;
+
+/// The names of the commands, in the order `help` lists them.
+pub const COMMANDS: [&str; 20] = [
+    "help", "state", "settings", "last", "adc", "entropy", "roll", "set", "preset", "sides", "pool", "modifier",
+    "keep", "count", "reroll", "dropped", "dice", "crit", "explode", "threshold",
+];
+
+/// The longest reply: the `last` roll of `MAX_DICE` dropped four-digit dice, each marked as exploded, followed
+/// by the modifier and the subtotals of `MAX_GROUPS` groups of dice. A whole reply is queued at once.
+const MAX_REPLY: usize = "-20099 =".len() + MAX_DICE * " (1000)!".len() + " -99".len()
+    + MAX_GROUPS * ", 20d1000: 20000".len() + ")\r\n".len();
+
+/// How many bytes can wait to be sent: the echo of a whole line followed by the longest reply.
+const OUTPUT_SIZE: usize = MAX_LINE + "\r\n".len() + MAX_REPLY;
+
+/// Roll detection thresholds that can be changed with the `threshold` command.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Threshold {
+    /// `Thresholds::min_force_amplitude`.
+    Force,
+    /// `Thresholds::ticks_to_disturb`.
+    Disturb,
+    /// `Thresholds::ticks_to_balance`.
+    Balance,
+    /// `Thresholds::ticks_to_sleep`.
+    Sleep,
+    /// `Thresholds::ticks_to_wake`.
+    Wake,
+}
+
+impl Threshold {
+    pub const ALL: [Threshold; 5] = [Threshold::Force, Threshold::Disturb, Threshold::Balance, Threshold::Sleep, Threshold::Wake];
+
+    /// Returns the name of the threshold used by the console.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Threshold::Force => "force",
+            Threshold::Disturb => "disturb",
+            Threshold::Balance => "balance",
+            Threshold::Sleep => "sleep",
+            Threshold::Wake => "wake",
+        }
+    }
+
+    /// Returns the current value of the threshold.
+    pub fn get(&self, thresholds: &Thresholds) -> u16 {
+        match self {
+            Threshold::Force => thresholds.min_force_amplitude,
+            Threshold::Disturb => thresholds.ticks_to_disturb as u16,
+            Threshold::Balance => thresholds.ticks_to_balance as u16,
+            Threshold::Sleep => thresholds.ticks_to_sleep,
+            Threshold::Wake => thresholds.ticks_to_wake as u16,
+        }
+    }
+
+    /// Changes the value of the threshold. Fails if the value doesn't fit the threshold. The tick counters
+    /// have to go past the disturb, sleep and wake thresholds, so these can't be the largest value of their type.
+    pub fn set(&self, thresholds: &mut Thresholds, value: u16) -> Result<(), Error> {
+        let below = |max: u16| if value < max { Ok(value) } else { Err(Error::InvalidArgument) };
+        match self {
+            Threshold::Force => thresholds.min_force_amplitude = value,
+            Threshold::Disturb => thresholds.ticks_to_disturb = below(u8::MAX as u16)? as u8,
+            Threshold::Balance => thresholds.ticks_to_balance = below(u8::MAX as u16 + 1)? as u8,
+            Threshold::Sleep => thresholds.ticks_to_sleep = below(u16::MAX)?,
+            Threshold::Wake => thresholds.ticks_to_wake = below(u8::MAX as u16)? as u8,
+        }
+        Ok(())
+    }
+}
+
+/// Commands accepted by the console.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Command {
+    Help,
+    State,
+    Settings,
+    Last,
+    Adc,
+    Entropy,
+    Roll,
+    /// Overrides the quantity and the quality, or gives the control back to the knobs if `None`.
+    Set(Option<(u8, u16)>),
+    /// Overrides all the settings with a parsed expression.
+    Preset(Program),
+    /// Changes the custom side count.
+    Sides(u16),
+    /// Adds a group of dice to the pool, or empties the pool if `None`.
+    Pool(Option<Group>),
+    Modifier(i8),
+    Keep(Mode),
+    /// Starts or stops counting successes.
+    Count(Mode),
+    Reroll(Reroll),
+    /// Enables or disables showing the dropped dice after a roll.
+    Dropped(bool),
+    /// Enables or disables showing every die after a roll.
+    Dice(bool),
+    /// Enables or disables animating critical rolls.
+    Critical(bool),
+    /// Enables or disables exploding dice.
+    Explode(bool),
+    /// Reports the thresholds if `None`, changes one of them otherwise.
+    Threshold(Option<(Threshold, u16)>),
+}
+
+/// Reasons for a command to fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    UnknownCommand,
+    InvalidArgument,
+    LineTooLong,
+    /// The settings haven't been read from the knobs yet.
+    NotReady,
+    /// The pool no longer fits along the selected dice.
+    TooManyDice,
+}
+
+impl Error {
+    /// Returns the description of the error reported by the console.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Error::UnknownCommand => "unknown command",
+            Error::InvalidArgument => "invalid argument",
+            Error::LineTooLong => "line too long",
+            Error::NotReady => "settings are not ready",
+            Error::TooManyDice => "too many dice",
+        }
+    }
+}
+
+/// Parses the quantity and the side count of dice in the `NdM` notation. N can be `H` or `L` for advantage
+/// and disadvantage or a quantity from 1 to `MAX_DICE`, M can be `F` for Fudge dice or a side count from 2
+/// to `MAX_SIDES`.
+fn parse_dice(dice: &str) -> Result<(u8, u16), Error> {
+    let (quantity, quality) = dice.split_once('d').ok_or(Error::InvalidArgument)?;
+    let quantity = match quantity {
+        "H" => ADVANTAGE,
+        "L" => DISADVANTAGE,
+        _ => quantity.parse().ok().filter(|n| (1..=MAX_DICE as u8).contains(n)).ok_or(Error::InvalidArgument)?,
+    };
+    let quality = match quality {
+        "F" => FUDGE,
+        _ => quality.parse().ok().filter(|sides| (2..=MAX_SIDES).contains(sides)).ok_or(Error::InvalidArgument)?,
+    };
+    Ok((quantity, quality))
+}
+
+/// Parses a single line. Returns `None` if the line is blank.
+pub fn parse(line: &[u8]) -> Result<Option<Command>, Error> {
+    let line = str::from_utf8(line).map_err(|_| Error::UnknownCommand)?;
+    let mut words = line.split_ascii_whitespace();
+    let Some(name) = words.next() else {
+        return Ok(None);
+    };
+
+    let command = match (name, words.next(), words.next()) {
+        ("help", None, None) => Command::Help,
+        ("state", None, None) => Command::State,
+        ("settings", None, None) => Command::Settings,
+        ("last", None, None) => Command::Last,
+        ("adc", None, None) => Command::Adc,
+        ("entropy", None, None) => Command::Entropy,
+        ("roll", None, None) => Command::Roll,
+        ("set", Some("pots"), None) => Command::Set(None),
+        ("set", Some(settings), None) => Command::Set(Some(parse_dice(settings)?)),
+        ("preset", Some(expression), None) => Command::Preset(notation::parse(expression).map_err(|_| Error::InvalidArgument)?),
+        ("sides", Some(sides), None) => Command::Sides(sides.parse().map_err(|_| Error::InvalidArgument)?),
+        ("pool", Some("clear"), None) => Command::Pool(None),
+        ("pool", Some(dice), None) => {
+            let (quantity, sides) = parse_dice(dice)?;
+            Command::Pool(Some(Group { quantity, sides }))
+        }
+        ("modifier", Some(modifier), None) => Command::Modifier(modifier.parse().map_err(|_| Error::InvalidArgument)?),
+        ("keep", Some("all"), None) => Command::Keep(Mode::Sum),
+        ("keep", Some(keep), None) => {
+            let (kind, k) = keep.split_at_checked(1).ok_or(Error::InvalidArgument)?;
+            let k = k.parse().map_err(|_| Error::InvalidArgument)?;
+            match kind {
+                "h" => Command::Keep(Mode::KeepHighest(k)),
+                "l" => Command::Keep(Mode::KeepLowest(k)),
+                _ => return Err(Error::InvalidArgument),
+            }
+        }
+        ("count", Some("off"), None) => Command::Count(Mode::Sum),
+        ("count", Some(target), botch) => {
+            let botches = match botch {
+                None => false,
+                Some("botch") => true,
+                Some(_) => return Err(Error::InvalidArgument),
+            };
+            let target = target.parse().ok().filter(|target| *target >= 1).ok_or(Error::InvalidArgument)?;
+            Command::Count(Mode::Successes { target, botches })
+        }
+        ("reroll", Some("off"), None) => Command::Reroll(Reroll::Never),
+        ("reroll", Some(face), always) => {
+            let face = face.parse().map_err(|_| Error::InvalidArgument)?;
+            match always {
+                None => Command::Reroll(Reroll::Once(face)),
+                Some("always") => Command::Reroll(Reroll::Always(face)),
+                Some(_) => return Err(Error::InvalidArgument),
+            }
+        }
+        ("dropped", Some("on"), None) => Command::Dropped(true),
+        ("dropped", Some("off"), None) => Command::Dropped(false),
+        ("dice", Some("on"), None) => Command::Dice(true),
+        ("dice", Some("off"), None) => Command::Dice(false),
+        ("crit", Some("on"), None) => Command::Critical(true),
+        ("crit", Some("off"), None) => Command::Critical(false),
+        ("explode", Some("on"), None) => Command::Explode(true),
+        ("explode", Some("off"), None) => Command::Explode(false),
+        ("threshold", None, None) => Command::Threshold(None),
+        ("threshold", Some(name), Some(value)) => {
+            let threshold = Threshold::ALL.into_iter()
+                .find(|t| t.name() == name)
+                .ok_or(Error::InvalidArgument)?;
+            let value = value.parse().map_err(|_| Error::InvalidArgument)?;
+            Command::Threshold(Some((threshold, value)))
+        }
+        (name, ..) if COMMANDS.contains(&name) => return Err(Error::InvalidArgument),
+        _ => return Err(Error::UnknownCommand),
+    };
+
+    if words.next().is_some() {
+        return Err(Error::InvalidArgument);
+    }
+    Ok(Some(command))
+}
+
+/// Collects received bytes into lines and queues bytes to be sent back.
+pub struct Console {
+    line: [u8; MAX_LINE],
+    len: usize,
+    /// Whether the current line has grown longer than `MAX_LINE`.
+    overflow: bool,
+    /// Bytes waiting to be sent.
+    output: Ring<OUTPUT_SIZE>,
+}
+
+impl Console {
+    pub const fn new() -> Self {
+        Self {
+            line: [0; MAX_LINE],
+            len: 0,
+            overflow: false,
+            output: Ring::new(),
+        }
+    }
+
+    /// Accepts a received byte and echoes it back. Once a whole line has been received, returns
+    /// the command parsed from it. Blank lines are ignored.
+    pub fn receive(&mut self, byte: u8) -> Option<Result<Command, Error>> {
+        match byte {
+            b'\r' | b'\n' => {
+                self.text("\r\n");
+                let result = if self.overflow { Err(Error::LineTooLong) } else { parse(&self.line[..self.len]) };
+                self.len = 0;
+                self.overflow = false;
+                result.transpose()
+            }
+            // backspace and delete erase the last character
+            0x08 | 0x7f => {
+                if self.len > 0 {
+                    self.len -= 1;
+                    self.text("\x08 \x08");
+                }
+                None
+            }
+            _ => {
+                if self.len < MAX_LINE {
+                    self.line[self.len] = byte;
+                    self.len += 1;
+                } else {
+                    self.overflow = true;
+                }
+                self.byte(byte);
+                None
+            }
+        }
+    }
+
+    /// Returns the next byte to be sent, if any.
+    pub fn read(&mut self) -> Option<u8> {
+        self.output.read()
+    }
+
+    /// Queues a byte to be sent. The byte is dropped if the output is full.
+    pub fn byte(&mut self, byte: u8) -> &mut Self {
+        if !self.output.is_full() {
+            self.output.write(byte);
+        }
+        self
+    }
+
+    /// Queues a text to be sent.
+    pub fn text(&mut self, text: &str) -> &mut Self {
+        for b in text.bytes() {
+            self.byte(b);
+        }
+        self
+    }
+
+    /// Queues a number to be sent in decimal.
+    pub fn number(&mut self, n: u16) -> &mut Self {
+        let mut digits = [0u8; 5];
+        let mut len = 0;
+        let mut n = n;
+        loop {
+            digits[len] = b'0' + (n % 10) as u8;
+            len += 1;
+            n /= 10;
+            if n == 0 {
+                break;
+            }
+        }
+        for d in digits[..len].iter().rev() {
+            self.byte(*d);
+        }
+        self
+    }
+
+    /// Queues the value of the quantity setting: a number, or `H` and `L` for advantage and disadvantage.
+    pub fn quantity(&mut self, quantity: u8) -> &mut Self {
+        match quantity {
+            ADVANTAGE => self.text("H"),
+            DISADVANTAGE => self.text("L"),
+            _ => self.number(quantity as u16),
+        }
+    }
+
+    /// Queues the side count of the dice: a number, or `F` for Fudge dice.
+    pub fn sides(&mut self, sides: u16) -> &mut Self {
+        match sides {
+            FUDGE => self.text("F"),
+            _ => self.number(sides),
+        }
+    }
+
+    /// Queues the face of a Fudge die thrown as a d3: `-`, `0` or `+`.
+    pub fn fudge(&mut self, die: u16) -> &mut Self {
+        self.text(match die {
+            1 => "-",
+            3 => "+",
+            _ => "0",
+        })
+    }
+
+    /// Queues the rerolled faces in the dice notation: `ro<2` for rerolling once, `r<2` for rerolling until
+    /// a higher face comes up. Nothing is queued if no throws are rerolled.
+    pub fn reroll(&mut self, reroll: Reroll) -> &mut Self {
+        match reroll {
+            Reroll::Never => self,
+            Reroll::Once(face) => self.text("ro<").number(face as u16),
+            Reroll::Always(face) => self.text("r<").number(face as u16),
+        }
+    }
+
+    /// Queues the mode in the dice notation, e.g. `kh3` or `>=7f1`. Nothing is queued if all the dice are
+    /// added together.
+    pub fn mode(&mut self, mode: Mode) -> &mut Self {
+        match mode {
+            Mode::Sum => self,
+            Mode::KeepHighest(k) => self.text("kh").number(k as u16),
+            Mode::KeepLowest(k) => self.text("kl").number(k as u16),
+            Mode::Successes { target, botches } => {
+                self.text(">=").number(target as u16);
+                if botches {
+                    self.text("f1");
+                }
+                self
+            }
+        }
+    }
+
+    /// Queues a signed number to be sent in decimal.
+    pub fn integer(&mut self, n: i16) -> &mut Self {
+        if n < 0 {
+            self.byte(b'-');
+        }
+        self.number(n.unsigned_abs())
+    }
+
+    /// Queues a modifier to be sent in decimal, always preceded by its sign.
+    pub fn modifier(&mut self, n: i8) -> &mut Self {
+        if n >= 0 {
+            self.byte(b'+');
+        }
+        self.integer(n as i16)
+    }
+
+    /// Queues the end of a reply.
+    pub fn end(&mut self) {
+        self.text("\r\n");
+    }
+
+    /// Queues a reply reporting the error.
+    pub fn error(&mut self, error: Error) {
+        self.text("error: ").text(error.message()).end();
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
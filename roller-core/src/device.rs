@@ -0,0 +1,1839 @@
+use crate::display::{self, Buffer, Display};
+use crate::entropy::{Generator, Health, Pool};
+use crate::hardware::{DisplayPort, Measurement, Sampler, TickRate, Timer};
+use crate::notation::Program;
+use crate::random;
+use crate::scales::{self, Zone, ADVANTAGE, CUSTOM, DISADVANTAGE, FUDGE, QUANTITY, QUALITY, SETUP};
+use crate::setup::{Menu, Page, Setting};
+use crate::utils::Agg;
+use crate::animation::{Spinner, BlinkingDot, Critical, Slideshow};
+
+#[cfg(feature = "console")]
+use crate::console::{Command, Console, Error, Threshold, COMMANDS};
+#[cfg(feature = "debug_spi")]
+use crate::protocol::{self, Message};
+use crate::protocol::StateId;
+#[cfg(feature = "debug_spi")]
+use crate::utils::Ring;
+
+/// How many of the latest measurements are stored.
+pub const AGG_SIZE: usize = 16;
+
+/// The largest number of dice rolled together.
+pub const MAX_DICE: usize = 20;
+
+/// The largest side count of a die, limited by the width of the display.
+pub const MAX_SIDES: u16 = 1000;
+
+/// The largest number of groups of dice rolled together, e.g. 2 for 2d6+1d8.
+pub const MAX_GROUPS: usize = 4;
+
+/// The largest absolute value of the modifier, limited by the width of the display.
+pub const MAX_MODIFIER: i8 = 99;
+
+/// How many bytes can wait to be sent over the debug channel: the frames queued by a single conversion, the
+/// measurements followed by the result of a roll and the new state, each at most `MAX_FRAME` long.
+#[cfg(feature = "debug_spi")]
+const DEBUG_SIZE: usize = 4 * protocol::MAX_FRAME;
+
+/// Defines specific device states.
+pub enum State {
+    Displaying { disturbed_ticks: u8, idle_ticks: u16, slides: Slideshow, critical: Option<Critical> },
+    Rolling {
+        params: [random::Params; MAX_GROUPS],
+        mode: Mode,
+        throws: Throws,
+        balanced_ticks: u8,
+        /// How many bits the entropy pool had harvested by the previous tick.
+        harvested: u16,
+        /// How many balanced ticks have passed without fresh bits.
+        starved_ticks: u16,
+        animation: Spinner,
+    },
+    Sleeping { disturbed_ticks: u8, animation: BlinkingDot },
+    /// The quality knob works the settings menu, a shake opens or closes its page.
+    Setup { menu: Menu, shaken_ticks: u8, idle_ticks: u16 },
+}
+
+impl State {
+    /// Returns the identifier of the state used by the debug channel.
+    pub fn id(&self) -> StateId {
+        match self {
+            State::Displaying { .. } => StateId::Displaying,
+            State::Rolling { .. } => StateId::Rolling,
+            State::Sleeping { .. } => StateId::Sleeping,
+            State::Setup { .. } => StateId::Setup,
+        }
+    }
+}
+
+/// Defines how the result of a roll is calculated from the dice.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Mode {
+    /// All the dice are added together.
+    Sum,
+    /// The specified number of the highest dice are added together, the rest are dropped.
+    KeepHighest(u8),
+    /// The specified number of the lowest dice are added together, the rest are dropped.
+    KeepLowest(u8),
+    /// The dice that meet the target are counted as successes. If `botches` is set, every 1 cancels a success.
+    Successes { target: u8, botches: bool },
+}
+
+/// Defines which throws are rerolled before a die is complete.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Reroll {
+    Never,
+    /// A throw of the specified face or lower is rerolled once, the second throw is kept.
+    Once(u8),
+    /// Throws of the specified face or lower are rerolled until a higher face comes up.
+    Always(u8),
+}
+
+/// A number of dice with the same side count, e.g. the 2d6 of 2d6+1d8.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Group {
+    pub quantity: u8,
+    /// The side count of the dice or `FUDGE`.
+    pub sides: u16,
+}
+
+impl Group {
+    /// A group without dice.
+    pub const EMPTY: Group = Group { quantity: 0, sides: 0 };
+}
+
+/// Returns the index of the group the die with the specified index belongs to, or 0 if it's past the groups.
+fn group_of(groups: &[Group], index: usize) -> usize {
+    let mut end = 0;
+    for (i, group) in groups.iter().enumerate() {
+        end += group.quantity as usize;
+        if index < end {
+            return i;
+        }
+    }
+    0
+}
+
+/// Collects the die throws of a roll. When the dice explode, a die that shows its maximum face is
+/// thrown again and the throws are added up, so a roll can take more throws than it has dice.
+/// Low throws are rerolled first, if enabled. The groups of a mixed roll are thrown one after another.
+pub struct Throws {
+    dice: [u16; MAX_DICE],
+    /// How many dice are complete.
+    count: u8,
+    /// The sum of the throws of the die in progress.
+    current: u16,
+    /// Whether the die in progress has been rerolled.
+    rerolled: bool,
+    /// Bit `i` is set if the die `i` has exploded.
+    exploded: u32,
+    /// How many times the dice have exploded.
+    explosions: u8,
+    groups: [Group; MAX_GROUPS],
+    group_count: u8,
+    /// The number of dice in all the groups.
+    quantity: u8,
+    exploding: bool,
+    reroll: Reroll,
+}
+
+impl Throws {
+    /// The largest number of extra throws per roll.
+    pub const MAX_EXPLOSIONS: u8 = 10;
+    /// The largest value of an exploded die, limited by the width of the display. Along with `MAX_EXPLOSIONS`,
+    /// it lets dice of every side count explode and keeps the sum of the dice within 16 bits.
+    pub const MAX_DIE: u16 = 9999;
+
+    /// Returns a new empty instance of Throws for the specified number of dice.
+    pub const fn new(quantity: u8, sides: u16, exploding: bool, reroll: Reroll) -> Self {
+        let mut groups = [Group::EMPTY; MAX_GROUPS];
+        groups[0] = Group { quantity, sides };
+        Self {
+            dice: [0; MAX_DICE],
+            count: 0,
+            current: 0,
+            rerolled: false,
+            exploded: 0,
+            explosions: 0,
+            groups,
+            group_count: 1,
+            quantity,
+            exploding,
+            reroll,
+        }
+    }
+
+    /// Adds a group of dice thrown after the others. Groups past `MAX_GROUPS` and dice past `MAX_DICE`
+    /// are left out.
+    pub fn add(&mut self, group: Group) {
+        let quantity = group.quantity.min((MAX_DICE as u8).saturating_sub(self.quantity));
+        if quantity == 0 || self.group_count as usize == MAX_GROUPS {
+            return;
+        }
+        self.groups[self.group_count as usize] = Group { quantity, ..group };
+        self.group_count += 1;
+        self.quantity += quantity;
+    }
+
+    /// Adds a throw to the die in progress. Once all the dice are complete, the next throw starts over.
+    pub fn put(&mut self, throw: u16) {
+        if self.is_complete() {
+            self.dice = [0; MAX_DICE];
+            self.count = 0;
+            self.current = 0;
+            self.rerolled = false;
+            self.exploded = 0;
+            self.explosions = 0;
+        }
+
+        let sides = self.groups[self.group()].sides;
+        // the throws that follow an explosion are never rerolled
+        if self.current == 0 && self.rerolls(throw, sides) {
+            self.rerolled = true;
+            return;
+        }
+
+        let sum = self.current.saturating_add(throw);
+        let explodes = self.exploding
+            && throw == sides
+            && self.explosions < Self::MAX_EXPLOSIONS
+            && sum.saturating_add(sides) <= Self::MAX_DIE;
+        if explodes {
+            self.current = sum;
+            self.explosions += 1;
+            self.exploded |= 1 << self.count;
+            return;
+        }
+
+        self.dice[self.count as usize] = sum;
+        self.count += 1;
+        self.current = 0;
+        self.rerolled = false;
+    }
+
+    /// Returns whether the throw is rerolled rather than added to the die in progress. Rerolling until
+    /// a higher face comes up is ignored if there's no higher face.
+    fn rerolls(&self, throw: u16, sides: u16) -> bool {
+        match self.reroll {
+            Reroll::Never => false,
+            Reroll::Once(face) => throw <= face as u16 && !self.rerolled,
+            Reroll::Always(face) => throw <= face as u16 && (face as u16) < sides,
+        }
+    }
+
+    /// Returns the index of the group the die in progress belongs to. Once all the dice are complete,
+    /// returns 0 as the next throw starts over.
+    pub fn group(&self) -> usize {
+        if self.is_complete() { 0 } else { group_of(self.groups(), self.count as usize) }
+    }
+
+    /// Returns the groups of dice.
+    pub fn groups(&self) -> &[Group] {
+        &self.groups[..self.group_count as usize]
+    }
+
+    /// Returns whether all the dice are complete.
+    pub fn is_complete(&self) -> bool {
+        self.count >= self.quantity
+    }
+
+    /// Returns the complete dice.
+    pub fn dice(&self) -> &[u16] {
+        &self.dice[..self.count as usize]
+    }
+
+    /// Returns how many times the dice have exploded.
+    pub fn explosions(&self) -> u8 {
+        self.explosions
+    }
+}
+
+/// The result of a roll.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Roll {
+    pub mode: Mode,
+    /// The sum of the kept dice.
+    pub sum: u16,
+    /// The modifier added to the sum.
+    pub modifier: i8,
+    /// The individual dice, only the first `count` are meaningful.
+    pub dice: [u16; MAX_DICE],
+    pub count: u8,
+    /// Bit `i` is set if the die `i` has been kept. When counting successes, the successful dice are kept.
+    pub kept: u32,
+    /// Bit `i` is set if the die `i` has exploded.
+    pub exploded: u32,
+    /// Bit `i` is set if the die `i` is a botch cancelling a success.
+    pub botched: u32,
+    /// Whether the dice are Fudge dice: 1, 2 and 3 stand for -1, 0 and +1.
+    pub fudge: bool,
+    /// The groups of dice, only the first `group_count` are meaningful.
+    pub groups: [Group; MAX_GROUPS],
+    pub group_count: u8,
+}
+
+impl Roll {
+    /// Returns the roll of the collected throws, or None if some of the dice aren't complete yet.
+    pub fn new(throws: &Throws, mode: Mode, modifier: i8) -> Option<Self> {
+        if !throws.is_complete() {
+            return None;
+        }
+
+        let dice_slice = throws.dice();
+        let kept = match mode {
+            Mode::Sum => Self::select(dice_slice, throws.count, |_, _| false),
+            Mode::KeepHighest(k) => Self::select(dice_slice, k, |a, b| a > b),
+            Mode::KeepLowest(k) => Self::select(dice_slice, k, |a, b| a < b),
+            Mode::Successes { target, .. } => Self::mask(dice_slice, |d| d >= target as u16),
+        };
+        let botched = match mode {
+            Mode::Successes { botches: true, .. } => Self::mask(dice_slice, |d| d == 1),
+            _ => 0,
+        };
+
+        let mut roll = Self {
+            mode,
+            sum: 0,
+            // the modifier only applies to sums
+            modifier: if matches!(mode, Mode::Successes { .. }) { 0 } else { modifier },
+            dice: throws.dice,
+            count: throws.count,
+            kept,
+            exploded: throws.exploded,
+            botched,
+            fudge: throws.groups[0].sides == FUDGE,
+            groups: throws.groups,
+            group_count: throws.group_count,
+        };
+        roll.sum = roll.kept_dice().sum();
+        Some(roll)
+    }
+
+    /// Picks `k` dice one by one, each time the first one of the remaining dice that no other remaining die
+    /// is `better` than. Returns the mask of the picked dice.
+    fn select(dice: &[u16], k: u8, better: impl Fn(u16, u16) -> bool) -> u32 {
+        let mut picked = 0u32;
+        for _ in 0..k.min(dice.len() as u8) {
+            let mut best: Option<usize> = None;
+            for (i, d) in dice.iter().enumerate() {
+                if picked & (1 << i) != 0 {
+                    continue;
+                }
+                if best.is_none_or(|b| better(*d, dice[b])) {
+                    best = Some(i);
+                }
+            }
+            if let Some(b) = best {
+                picked |= 1 << b;
+            }
+        }
+        picked
+    }
+
+    /// Returns the mask of the dice that match the predicate.
+    fn mask(dice: &[u16], predicate: impl Fn(u16) -> bool) -> u32 {
+        dice.iter().enumerate().filter(|(_, d)| predicate(**d)).fold(0, |mask, (i, _)| mask | 1 << i)
+    }
+
+    /// Returns the individual dice.
+    pub fn dice(&self) -> &[u16] {
+        &self.dice[..self.count as usize]
+    }
+
+    /// Returns the groups of dice.
+    pub fn groups(&self) -> &[Group] {
+        &self.groups[..self.group_count as usize]
+    }
+
+    /// Returns the side count of the die with the specified index.
+    pub fn sides(&self, index: usize) -> u16 {
+        self.groups[group_of(self.groups(), index)].sides
+    }
+
+    /// Returns whether the die with the specified index has been kept.
+    pub fn is_kept(&self, index: usize) -> bool {
+        self.kept & (1 << index) != 0
+    }
+
+    /// Returns whether the die with the specified index has exploded.
+    pub fn is_exploded(&self, index: usize) -> bool {
+        self.exploded & (1 << index) != 0
+    }
+
+    /// Returns the dice added to the sum.
+    pub fn kept_dice(&self) -> impl Iterator<Item=u16> + '_ {
+        self.dice().iter().enumerate().filter(|(i, _)| self.is_kept(*i)).map(|(_, d)| *d)
+    }
+
+    /// Returns the dice left out of the sum.
+    pub fn dropped_dice(&self) -> impl Iterator<Item=u16> + '_ {
+        self.dice().iter().enumerate().filter(|(i, _)| !self.is_kept(*i)).map(|(_, d)| *d)
+    }
+
+    /// Returns whether the only kept die shows the maximum face (`Some(true)`) or the minimum one (`Some(false)`),
+    /// regardless of the modifier. Exploded dice count as the maximum face. Only a single die or the kept one
+    /// of two dice, like with advantage, can be critical: the highest of 4d6 is often a 6. Rolls of Fudge dice
+    /// and rolls counting successes are never critical.
+    pub fn critical(&self) -> Option<bool> {
+        if self.fudge || matches!(self.mode, Mode::Successes { .. }) || self.count > 2 || self.kept.count_ones() != 1 {
+            return None;
+        }
+        let index = (0..self.count as usize).find(|i| self.is_kept(*i))?;
+        match self.dice[index] {
+            die if die >= self.sides(index) => Some(true),
+            1 => Some(false),
+            _ => None,
+        }
+    }
+
+    /// Returns the displayed number: the sum of the dice with the modifier applied, or the number of successes
+    /// less the botches.
+    pub fn total(&self) -> i16 {
+        match self.mode {
+            Mode::Successes { .. } => self.kept.count_ones() as i16 - self.botched.count_ones() as i16,
+            _ if self.fudge => self.kept_dice().map(|d| d as i16 - 2).sum::<i16>() + self.modifier as i16,
+            _ => self.sum as i16 + self.modifier as i16,
+        }
+    }
+
+    /// Returns the subtotal of every group of dice: the sum of its kept dice, or its number of successes less
+    /// the botches. The modifier only applies to the total.
+    pub fn subtotals(&self) -> impl Iterator<Item=i16> + '_ {
+        let mut start = 0;
+        self.groups().iter().map(move |group| {
+            let dice = start..start + group.quantity as usize;
+            start = dice.end;
+            match self.mode {
+                Mode::Successes { .. } => {
+                    let mask = dice.fold(0u32, |mask, i| mask | 1 << i);
+                    (self.kept & mask).count_ones() as i16 - (self.botched & mask).count_ones() as i16
+                }
+                _ => dice
+                    .filter(|i| self.is_kept(*i))
+                    .map(|i| if self.fudge { self.dice[i] as i16 - 2 } else { self.dice[i] as i16 })
+                    .sum(),
+            }
+        })
+    }
+}
+
+/// Thresholds of the roll detection.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Thresholds {
+    /// Minimal force amplitude to be considered a disturbance, measured in ADC steps (1/256g).
+    pub min_force_amplitude: u16,
+    /// How many ticks a disturbance has to last to start a roll.
+    pub ticks_to_disturb: u8,
+    /// How many ticks the device has to stay balanced to finish a roll.
+    pub ticks_to_balance: u8,
+    /// How many idle ticks make the device fall asleep.
+    pub ticks_to_sleep: u16,
+    /// How many ticks at the sleeping rate a disturbance has to last to wake the device up.
+    pub ticks_to_wake: u8,
+}
+
+/// Defines general device state and behavior.
+pub struct Device<P: DisplayPort, S: Sampler, T: Timer> {
+    /// Hardware driven by the device.
+    pub port: P,
+    pub sampler: S,
+    pub timer: T,
+
+    display: Display,
+    state: State,
+
+    /// Currently active settings. Uninitialized for the first few moments after the startup.
+    quantity: Option<&'static Zone>,
+    quality: Option<&'static Zone<u16>>,
+    /// Whether the quantity knob is in the setup zone. The selected dice stay the same meanwhile.
+    setup: bool,
+    /// How many ticks the quantity has stayed selected with the knob.
+    quantity_ticks: u16,
+    /// The latest quantity that has stayed selected long enough, the knob passes the others on the way to
+    /// the setup zone.
+    settled: Option<&'static Zone>,
+    /// The side count selected by the custom quality zone.
+    custom_sides: u16,
+    /// Added to the sum of every roll.
+    modifier: i8,
+    /// Defines how the result is calculated from the dice, unless a mode is selected with the quantity knob.
+    mode: Mode,
+    /// Whether the dropped dice are shown after the total of a roll.
+    show_dropped: bool,
+    /// Whether every die is shown after the total of a roll.
+    show_dice: bool,
+    /// Whether critical rolls are animated before the total.
+    show_critical: bool,
+    /// Whether the dice that show their maximum face are thrown again.
+    exploding: bool,
+    /// Which throws are rerolled.
+    reroll: Reroll,
+    /// Groups of dice rolled along with the selected dice, e.g. the 1d8 of 2d6+1d8. Only the first
+    /// `pool_len` are meaningful.
+    pool: [Group; MAX_GROUPS - 1],
+    pool_len: u8,
+
+    /// What's currently being measured by the ADC.
+    adc_measuring: Option<Measurement>,
+
+    /// Aggregations of recentl measurement results.
+    acc_x: AccAxis,
+    acc_y: AccAxis,
+    acc_z: AccAxis,
+    pot_quantity: Agg<u16, AGG_SIZE>,
+    pot_quality: Agg<u16, AGG_SIZE>,
+
+    /// Whitens the noise of the accelerometer measurements into bits for the generator.
+    entropy: Pool,
+    /// Generates the die throws, reseeded from the entropy pool.
+    rng: Generator,
+    /// Health tests of the accelerometer axes, in the order of the measurements.
+    health: [Health; 3],
+
+    /// The result of the latest roll.
+    last_roll: Option<Roll>,
+    thresholds: Thresholds,
+
+    /// The latest results of every measurement, indexed by `Measurement`.
+    #[cfg(any(feature = "debug_spi", feature = "console"))]
+    measurements: [u16; 5],
+
+    /// Bytes waiting to be sent over the debug channel.
+    #[cfg(feature = "debug_spi")]
+    pub debug_buf: Ring<DEBUG_SIZE>,
+
+    /// The serial console.
+    #[cfg(feature = "console")]
+    pub console: Console,
+    /// Whether the settings have been set over the console rather than with the knobs.
+    #[cfg(feature = "console")]
+    settings_overridden: bool,
+}
+
+/// Container for measurement aggregations of a single accelerometer axis.
+pub struct AccAxis {
+    /// The latest measurements.
+    pub l1: Agg<u16, AGG_SIZE>,
+    /// The latest averages of the first level.
+    pub l2: Agg<u16, AGG_SIZE>,
+}
+
+impl AccAxis {
+    /// Returns a new instance of AccAxis.
+    pub const fn new() -> Self {
+        Self {
+            l1: Agg::new(),
+            l2: Agg::new(),
+        }
+    }
+
+    /// Adds a new measurement to the first level. Once the first level is full, every new
+    /// measurement also adds the first level's average to the second level.
+    pub fn put(&mut self, measurement: u16) {
+        self.l1.put(measurement);
+        if let Some(avg) = self.l1.avg_full::<u16>() {
+            self.l2.put(avg);
+        }
+    }
+}
+
+impl Default for AccAxis {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: DisplayPort, S: Sampler, T: Timer> Device<P, S, T> {
+    pub const NORMAL_FREQUENCY: u8 = 200;
+    pub const SLEEPING_FREQUENCY: u8 = 50;
+
+    pub const DEFAULT_CUSTOM_SIDES: u16 = 30;
+
+    /// How long the dice and the modifier are shown in the settings view.
+    const SETTINGS_DICE_TICKS: u16 = Self::NORMAL_FREQUENCY as u16 * 3 / 2;
+    const SETTINGS_MODIFIER_TICKS: u16 = Self::NORMAL_FREQUENCY as u16 * 3 / 4;
+
+    /// How long the quantity and the side count are shown when they don't fit on the display together.
+    const SETTINGS_HALF_TICKS: u16 = Self::NORMAL_FREQUENCY as u16;
+
+    /// How long the settings view shows the keep mode.
+    const SETTINGS_MODE_TICKS: u16 = Self::NORMAL_FREQUENCY as u16 * 3 / 4;
+
+    /// How long the quantity has to stay selected to be added to the pool from the settings menu.
+    const TICKS_TO_SETTLE: u16 = Self::NORMAL_FREQUENCY as u16 / 2;
+
+    /// How long the total and then the dice are shown after a roll with dropped dice.
+    const ROLL_KEPT_TICKS: u16 = Self::NORMAL_FREQUENCY as u16 * 2;
+    const ROLL_DICE_TICKS: u16 = Self::NORMAL_FREQUENCY as u16 * 3 / 2;
+
+    /// How long the index of a die is shown on its own before a value that leaves no room for it.
+    const ROLL_INDEX_TICKS: u16 = Self::NORMAL_FREQUENCY as u16 / 2;
+
+    /// How long every group of mixed dice is shown after a roll, before its subtotal.
+    const ROLL_GROUP_TICKS: u16 = Self::NORMAL_FREQUENCY as u16 * 3 / 4;
+
+    /// "Err", shown instead of the result when the accelerometer fails the health tests during a roll or stops
+    /// producing fresh bits, and instead of the pool when it no longer fits along the selected dice.
+    const HEALTH_FAILURE: Buffer = [display::symbol::ECHO, display::symbol::ROMEO, display::symbol::ROMEO, 0];
+
+    /// How long a roll waits for fresh bits once the device has come to rest before it fails the same way: the
+    /// lowest bits of the accelerometer have stopped changing.
+    const TICKS_TO_STARVE: u16 = Self::NORMAL_FREQUENCY as u16 * 2;
+
+    pub const DEFAULT_THRESHOLDS: Thresholds = Thresholds {
+        min_force_amplitude: 40, // ~0.156g
+        ticks_to_disturb: (Self::NORMAL_FREQUENCY as f64 * 0.35) as u8,
+        ticks_to_balance: (Self::NORMAL_FREQUENCY as f64 * 0.6) as u8,
+        ticks_to_sleep: Self::NORMAL_FREQUENCY as u16 * 30,
+        ticks_to_wake: (Self::SLEEPING_FREQUENCY as f64 * 0.4) as u8,
+    };
+
+    /// Returns a new instance of Device driving the provided hardware.
+    pub const fn new(port: P, sampler: S, timer: T) -> Self {
+        Device {
+            port,
+            sampler,
+            timer,
+            display: Display::new(),
+            adc_measuring: None,
+
+            entropy: Pool::new(),
+            rng: Generator::new([0; 4]),
+            health: [Health::new(), Health::new(), Health::new()],
+            state: State::Displaying { disturbed_ticks: 0, idle_ticks: 0, slides: Slideshow::new(), critical: None },
+
+            pot_quantity: Agg::new(),
+            pot_quality: Agg::new(),
+            acc_x: AccAxis::new(),
+            acc_y: AccAxis::new(),
+            acc_z: AccAxis::new(),
+
+            quantity: None,
+            quality: None,
+            setup: false,
+            quantity_ticks: 0,
+            settled: None,
+            custom_sides: Self::DEFAULT_CUSTOM_SIDES,
+            modifier: 0,
+            mode: Mode::Sum,
+            show_dropped: false,
+            show_dice: false,
+            show_critical: true,
+            exploding: false,
+            reroll: Reroll::Never,
+            pool: [Group::EMPTY; MAX_GROUPS - 1],
+            pool_len: 0,
+
+            last_roll: None,
+            thresholds: Self::DEFAULT_THRESHOLDS,
+
+            #[cfg(any(feature = "debug_spi", feature = "console"))]
+            measurements: [0; 5],
+
+            #[cfg(feature = "debug_spi")]
+            debug_buf: Ring::new(),
+
+            #[cfg(feature = "console")]
+            console: Console::new(),
+            #[cfg(feature = "console")]
+            settings_overridden: false,
+        }
+    }
+
+    /// Returns the current device state.
+    pub fn state(&self) -> &State {
+        &self.state
+    }
+
+    /// Returns the display driven by the device.
+    pub fn display(&self) -> &Display {
+        &self.display
+    }
+
+    /// Returns the modifier added to the sum of every roll.
+    pub fn modifier(&self) -> i8 {
+        self.modifier
+    }
+
+    /// Changes the modifier added to the sum of every roll, shows the new settings.
+    /// The modifier is clamped to `MAX_MODIFIER` in either direction.
+    pub fn set_modifier(&mut self, modifier: i8) {
+        self.modifier = modifier.clamp(-MAX_MODIFIER, MAX_MODIFIER);
+        self.show_settings();
+    }
+
+    /// Returns the side count selected by the custom quality zone.
+    pub fn custom_sides(&self) -> u16 {
+        self.custom_sides
+    }
+
+    /// Changes the side count selected by the custom quality zone, shows the new settings.
+    /// Dice have at least 2 and at most `MAX_SIDES` sides.
+    pub fn set_custom_sides(&mut self, sides: u16) {
+        self.custom_sides = sides.clamp(2, MAX_SIDES);
+        self.show_settings();
+    }
+
+    /// Returns the side count selected by the quality zone.
+    fn sides(&self, quality: &Zone<u16>) -> u16 {
+        if quality.value == CUSTOM { self.custom_sides } else { quality.value }
+    }
+
+    /// Returns how the result is calculated from the dice, unless a mode is selected with the quantity knob.
+    pub fn mode(&self) -> Mode {
+        self.mode
+    }
+
+    /// Changes how the result is calculated from the dice, shows the new settings.
+    /// Keeping no dice is the same as adding all of them together.
+    pub fn set_mode(&mut self, mode: Mode) {
+        self.mode = match mode {
+            Mode::KeepHighest(0) | Mode::KeepLowest(0) => Mode::Sum,
+            _ => mode,
+        };
+        self.show_settings();
+    }
+
+    /// Returns whether the dropped dice are shown after the total of a roll.
+    pub fn show_dropped(&self) -> bool {
+        self.show_dropped
+    }
+
+    /// Changes whether the dropped dice are shown after the total of a roll.
+    pub fn set_show_dropped(&mut self, show: bool) {
+        self.show_dropped = show;
+    }
+
+    /// Returns whether every die is shown after the total of a roll.
+    pub fn show_dice(&self) -> bool {
+        self.show_dice
+    }
+
+    /// Changes whether every die is shown after the total of a roll.
+    pub fn set_show_dice(&mut self, show: bool) {
+        self.show_dice = show;
+    }
+
+    /// Returns whether critical rolls are animated before the total.
+    pub fn show_critical(&self) -> bool {
+        self.show_critical
+    }
+
+    /// Changes whether critical rolls are animated before the total.
+    pub fn set_show_critical(&mut self, show: bool) {
+        self.show_critical = show;
+    }
+
+    /// Returns whether the dice that show their maximum face are thrown again.
+    pub fn exploding(&self) -> bool {
+        self.exploding
+    }
+
+    /// Changes whether the dice that show their maximum face are thrown again.
+    pub fn set_exploding(&mut self, exploding: bool) {
+        self.exploding = exploding;
+    }
+
+    /// Returns which throws are rerolled.
+    pub fn reroll(&self) -> Reroll {
+        self.reroll
+    }
+
+    /// Changes which throws are rerolled, shows the new settings. Rerolling throws of face 0 is the same
+    /// as never rerolling.
+    pub fn set_reroll(&mut self, reroll: Reroll) {
+        self.reroll = match reroll {
+            Reroll::Once(0) | Reroll::Always(0) => Reroll::Never,
+            _ => reroll,
+        };
+        self.show_settings();
+    }
+
+    /// Returns the groups of dice rolled along with the selected dice.
+    pub fn pool(&self) -> &[Group] {
+        &self.pool[..self.pool_len as usize]
+    }
+
+    /// Adds a group of dice rolled along with the selected dice, shows the new settings. Returns false and
+    /// leaves the pool alone if the group can't join the pool, see `fits`.
+    pub fn add_to_pool(&mut self, group: Group) -> bool {
+        let selected = self.selected().filter(|g| Self::pooled(g.quantity, g.sides)).map_or(0, |g| g.quantity as usize);
+        let pooled = self.pool().iter().map(|g| g.quantity as usize).sum::<usize>();
+        if !Self::fits(group, self.pool_len as usize, selected + pooled) {
+            return false;
+        }
+        self.pool[self.pool_len as usize] = group;
+        self.pool_len += 1;
+        self.show_settings();
+        true
+    }
+
+    /// Returns whether the group of dice can join a pool of `groups` groups rolled along with `dice` dice in all:
+    /// it has dice of 2 to `MAX_SIDES` sides, there's room for it in the pool and it doesn't take the dice past
+    /// `MAX_DICE`.
+    fn fits(group: Group, groups: usize, dice: usize) -> bool {
+        group.quantity > 0
+            && (2..=MAX_SIDES).contains(&group.sides)
+            && groups < MAX_GROUPS - 1
+            && dice + group.quantity as usize <= MAX_DICE
+    }
+
+    /// Removes every group of dice rolled along with the selected dice, shows the new settings.
+    pub fn clear_pool(&mut self) {
+        self.pool_len = 0;
+        self.show_settings();
+    }
+
+    /// Returns the dice selected with the knobs or over the console, if the settings have been initialized.
+    /// The quantity can be `ADVANTAGE` or `DISADVANTAGE`, the side count can be `FUDGE`.
+    pub fn selected(&self) -> Option<Group> {
+        let (quantity, quality) = (self.quantity?, self.quality?);
+        Some(Group { quantity: quantity.value, sides: self.sides(quality) })
+    }
+
+    /// Makes the parsed program the active settings: the first group of dice is selected like with the knobs,
+    /// the rest of them replace the pool. Over the console, the knobs are ignored until `set pots`. Returns
+    /// false and keeps the settings if the first group can't be selected or the rest can't join the pool
+    /// the way `add_to_pool` would let them.
+    pub fn load(&mut self, program: &Program) -> bool {
+        let [first, pool @ ..] = program.groups() else {
+            return false;
+        };
+        if !pool.is_empty() && !Self::pooled(first.quantity, first.sides) {
+            return false;
+        }
+        let mut dice = first.quantity as usize;
+        for (i, group) in pool.iter().enumerate() {
+            if !Self::fits(*group, i, dice) {
+                return false;
+            }
+            dice += group.quantity as usize;
+        }
+        if !self.select(first.quantity, first.sides) {
+            return false;
+        }
+        self.pool_len = 0;
+        for group in pool {
+            self.pool[self.pool_len as usize] = *group;
+            self.pool_len += 1;
+        }
+        self.mode = program.mode;
+        self.modifier = program.modifier.clamp(-MAX_MODIFIER, MAX_MODIFIER);
+        self.exploding = program.exploding;
+        self.reroll = program.reroll;
+        self.show_settings();
+        true
+    }
+
+    /// Selects the quantity and the side count regardless of the knobs. Side counts missing from the scale
+    /// are selected with the custom zone. Returns false if either of them can't be selected.
+    fn select(&mut self, quantity: u8, sides: u16) -> bool {
+        let Some(quantity) = QUANTITY.iter().find(|z| z.value == quantity && quantity != SETUP) else {
+            return false;
+        };
+        if sides == CUSTOM || sides > MAX_SIDES {
+            return false;
+        }
+        let Some(quality) = QUALITY.iter()
+            .find(|z| z.value == sides)
+            .or_else(|| QUALITY.iter().find(|z| z.value == CUSTOM)) else {
+            return false;
+        };
+        if quality.value == CUSTOM {
+            self.custom_sides = sides;
+        }
+        self.quantity = Some(quantity);
+        self.quality = Some(quality);
+        #[cfg(feature = "console")]
+        {
+            self.settings_overridden = true;
+        }
+        true
+    }
+
+    /// Returns whether the dice of the pool are rolled along with the selected ones. Advantage, disadvantage
+    /// and Fudge dice are rolled on their own.
+    fn pooled(quantity: u8, sides: u16) -> bool {
+        !matches!(quantity, ADVANTAGE | DISADVANTAGE) && sides != FUDGE
+    }
+
+    /// Returns whether the pool can be rolled along with the selected dice without taking them past `MAX_DICE`.
+    /// The pool may no longer fit once the quantity knob has selected more dice.
+    fn pool_fits(&self, quantity: u8, sides: u16) -> bool {
+        !Self::pooled(quantity, sides)
+            || quantity as usize + self.pool().iter().map(|g| g.quantity as usize).sum::<usize>() <= MAX_DICE
+    }
+
+    /// Returns the result of the latest roll, if there has been one.
+    pub fn last_roll(&self) -> Option<&Roll> {
+        self.last_roll.as_ref()
+    }
+
+    /// Returns the pool of whitened bits the generator is reseeded from.
+    pub fn entropy(&self) -> &Pool {
+        &self.entropy
+    }
+
+    /// Returns the thresholds the roll detection currently works with.
+    pub fn thresholds(&self) -> &Thresholds {
+        &self.thresholds
+    }
+
+    /// Uses the latest averaged measurements of potentiometer channels to detect if the settings
+    /// have been changed. If either of the settings has changed, displays the new settings. In the setup
+    /// zone of the quantity knob, follows the quality knob through the settings menu instead.
+    pub fn test_pots(&mut self) {
+        // the knobs are ignored while the settings are overridden over the console
+        #[cfg(feature = "console")]
+        if self.settings_overridden {
+            return;
+        }
+
+        let mut render = false;
+
+        self.quantity_ticks = self.quantity_ticks.saturating_add(1);
+        if self.quantity_ticks == Self::TICKS_TO_SETTLE && !self.setup {
+            self.settled = self.quantity;
+        }
+        let quantity = if self.setup { QUANTITY.iter().find(|z| z.value == SETUP) } else { self.quantity };
+        if let Some(new) = Self::test_pot(&self.pot_quantity, quantity, &QUANTITY[..]) {
+            self.quantity_ticks = 0;
+            self.setup = new.value == SETUP;
+            if self.setup {
+                // the quantities passed on the way aren't the selected dice
+                if self.settled.is_some() {
+                    self.quantity = self.settled;
+                }
+                self.enter_setup();
+                return;
+            }
+            render = true;
+            self.quantity = Some(new);
+        }
+
+        if self.setup {
+            let position = self.pot_quality.avg_full::<u16>();
+            if let (State::Setup { menu, idle_ticks, .. }, Some(position)) = (&mut self.state, position) {
+                if menu.turn(position) {
+                    *idle_ticks = 0;
+                    let menu = *menu;
+                    self.render_menu(menu);
+                }
+            }
+            return;
+        }
+
+        if let Some(new) = Self::test_pot(&self.pot_quality, self.quality, &QUALITY[..]) {
+            render = true;
+            self.quality = Some(new);
+        }
+
+        if render {
+            self.show_settings();
+        }
+    }
+
+    /// Displays the current settings, if they have been initialized. The settings menu shows the new value
+    /// of its page instead until the quantity knob leaves the setup zone.
+    fn show_settings(&mut self) {
+        if let (true, State::Setup { menu, .. }) = (self.setup, &self.state) {
+            self.render_menu(*menu);
+            return;
+        }
+        if let (Some(quantity), Some(quality)) = (self.quantity, self.quality) {
+            self.enter_displaying();
+            self.render_settings(quantity.value, self.sides(quality));
+        }
+    }
+
+    /// Determine whether the new position reported by the pot should lead to a change of the current setting.
+    fn test_pot<V>(pos: &Agg<u16, AGG_SIZE>, current: Option<&'static Zone<V>>, scale: &'static [Zone<V>]) -> Option<&'static Zone<V>> {
+        let avg = pos.avg_full::<u16>()?;
+
+        if let Some(s) = current {
+            return scales::detect_zone_change(avg, 10, s, scale);
+        }
+        Some(scales::detect_zone(avg, scale))
+    }
+
+    /// Render the currently active "quantity" and "quality" settings and write to the display, e.g. "3d6"
+    /// or "4dF". Longer side counts are shown in turns with the quantity, e.g. "20d" and "d100" or "1000". The pooled
+    /// dice, the keep mode, the rerolled faces and a non-zero modifier are shown in turns with the dice, this
+    /// must be called in the "Displaying" state.
+    fn render_settings(&mut self, quantity: u8, quality: u16) {
+        let (dice_buf, sides_buf) = Self::render_group(quantity, quality);
+        self.display.buffer = dice_buf;
+
+        let pool = if Self::pooled(quantity, quality) { &self.pool[..self.pool_len as usize] } else { &[] };
+        let fits = self.pool_fits(quantity, quality);
+        // the knob positions past 1 select their own mode
+        let mode = if matches!(quantity, ADVANTAGE | DISADVANTAGE) { Mode::Sum } else { self.mode };
+        if sides_buf.is_none() && pool.is_empty() && mode == Mode::Sum && self.reroll == Reroll::Never && self.modifier == 0 {
+            return;
+        }
+
+        let State::Displaying { slides, .. } = &mut self.state else {
+            return;
+        };
+        Self::push_group(slides, (dice_buf, sides_buf), Self::SETTINGS_DICE_TICKS);
+        if fits {
+            for group in pool {
+                Self::push_group(slides, Self::render_group(group.quantity, group.sides), Self::SETTINGS_DICE_TICKS);
+            }
+        } else {
+            // the roll would leave some of the dice out, it's refused until fewer dice are selected
+            slides.push(Self::HEALTH_FAILURE, Self::SETTINGS_DICE_TICKS);
+        }
+
+        match mode {
+            Mode::KeepHighest(k) | Mode::KeepLowest(k) => {
+                // "H3" for keeping the 3 highest dice, "L3" for keeping the 3 lowest ones
+                let mut mode_buf = [0u8; 4];
+                mode_buf[0] = if matches!(mode, Mode::KeepHighest(_)) { display::symbol::HOTEL } else { display::symbol::LIMA };
+                let mode_len = 1 + display::encode_u8_into(&mut mode_buf[1..], k);
+                mode_buf.rotate_right(4 - mode_len);
+                slides.push(mode_buf, Self::SETTINGS_MODE_TICKS);
+            }
+            Mode::Successes { target, botches } => {
+                // the bars followed by the target, with a dot if 1s are botches
+                let mut mode_buf = [0u8; 4];
+                let target_len = display::encode_u8_into(&mut mode_buf[1..], target);
+                mode_buf[1..].rotate_right(3 - target_len);
+                mode_buf[0] = display::symbol::BARS | if botches { display::segment::POINT } else { 0 };
+                slides.push(mode_buf, Self::SETTINGS_MODE_TICKS);
+            }
+            Mode::Sum => {}
+        }
+
+        if let Reroll::Once(face) | Reroll::Always(face) = self.reroll {
+            // "r2" for rerolling 1s and 2s once, "rr2" for rerolling them until a higher face comes up
+            let mut reroll_buf = [0u8; 4];
+            let face_len = display::encode_u8_into(&mut reroll_buf[1..], face);
+            reroll_buf[1..].rotate_right(3 - face_len);
+            reroll_buf[3 - face_len] = display::symbol::ROMEO;
+            // three-digit faces leave no room for the second "r"
+            if matches!(self.reroll, Reroll::Always(_)) && face_len < 3 {
+                reroll_buf[2 - face_len] = display::symbol::ROMEO;
+            }
+            slides.push(reroll_buf, Self::SETTINGS_MODE_TICKS);
+        }
+
+        // the modifier isn't added to the number of successes
+        if self.modifier != 0 && !matches!(mode, Mode::Successes { .. }) {
+            let mut modifier_buf = [0u8; 4];
+            let modifier_len = display::encode_i16_into(&mut modifier_buf, self.modifier as i16, true);
+            modifier_buf.rotate_right(4 - modifier_len);
+            slides.push(modifier_buf, Self::SETTINGS_MODIFIER_TICKS);
+        }
+    }
+
+    /// Returns the current value of the setting of the menu page.
+    fn setting(&self, page: Page) -> Setting {
+        match page {
+            Page::Modifier => Setting::Modifier(self.modifier),
+            Page::Keep => Setting::Keep(self.mode),
+            Page::Dropped => Setting::Dropped(self.show_dropped),
+            Page::Exploding => Setting::Exploding(self.exploding),
+            Page::Count => Setting::Count(match self.mode {
+                Mode::Successes { target, botches } => Some((target, botches)),
+                _ => None,
+            }),
+            Page::Dice => Setting::Dice(self.show_dice),
+            Page::Sides => Setting::Sides(self.custom_sides),
+            Page::Critical => Setting::Critical(self.show_critical),
+            Page::Reroll => Setting::Reroll(self.reroll),
+            Page::Pool => Setting::Pooled(self.pool_len),
+        }
+    }
+
+    /// Applies the value picked in the settings menu.
+    fn apply(&mut self, setting: Setting) {
+        match setting {
+            Setting::Modifier(modifier) => self.modifier = modifier,
+            // keeping all the dice leaves counting the successes alone, and the other way around
+            Setting::Keep(Mode::Sum) => if !matches!(self.mode, Mode::Successes { .. }) {
+                self.mode = Mode::Sum;
+            },
+            Setting::Keep(mode) => self.mode = mode,
+            Setting::Dropped(show) => self.show_dropped = show,
+            Setting::Exploding(exploding) => self.exploding = exploding,
+            Setting::Count(Some((target, botches))) => self.mode = Mode::Successes { target, botches },
+            Setting::Count(None) => if matches!(self.mode, Mode::Successes { .. }) {
+                self.mode = Mode::Sum;
+            },
+            Setting::Dice(show) => self.show_dice = show,
+            Setting::Sides(sides) => self.custom_sides = sides,
+            Setting::Critical(show) => self.show_critical = show,
+            Setting::Reroll(reroll) => self.reroll = reroll,
+            Setting::Pool(true) => if let Some(group) = self.selected().filter(|g| Self::pooled(g.quantity, g.sides)) {
+                self.add_to_pool(group);
+            },
+            Setting::Pool(false) => self.clear_pool(),
+            Setting::Pooled(_) => {}
+        }
+    }
+
+    /// Renders the settings menu with the current value of the setting of its page.
+    fn render_menu(&mut self, menu: Menu) {
+        self.display.buffer = menu.render(self.setting(menu.page()));
+    }
+
+    /// Renders a group of dice, e.g. "3d6" or "4dF". Returns a second buffer with the side count if it has
+    /// three digits, e.g. "20d" and "d100". Four-digit side counts leave no room for the "d", e.g. "1000".
+    fn render_group(quantity: u8, quality: u16) -> (Buffer, Option<Buffer>) {
+        let mut quantity_buf = [0u8; 2];
+        let quantity_len = match quantity {
+            // "H" for keeping the higher die, "L" for keeping the lower one
+            ADVANTAGE => { quantity_buf[0] = display::symbol::HOTEL; 1 }
+            DISADVANTAGE => { quantity_buf[0] = display::symbol::LIMA; 1 }
+            _ => display::encode_u8_into(&mut quantity_buf, quantity),
+        };
+
+        let mut quality_buf = [0u8; 4];
+        let quality_len = match quality {
+            FUDGE => { quality_buf[0] = display::symbol::FOXTROT; 1 }
+            _ => display::encode_u16_into(&mut quality_buf, quality),
+        };
+
+        let mut sides_buf = None;
+
+        let dice_buf = match (quantity_len, quality_len) {
+            (1, 1) => [
+                0,
+                quantity_buf[0],
+                display::symbol::DELTA,
+                quality_buf[0],
+            ],
+            (2, 1) => [
+                quantity_buf[0],
+                quantity_buf[1],
+                display::symbol::DELTA,
+                quality_buf[0],
+            ],
+            (1, 2) => [
+                quantity_buf[0],
+                display::symbol::DELTA,
+                quality_buf[0],
+                quality_buf[1],
+            ],
+            (2, 2) => [
+                quantity_buf[0],
+                quantity_buf[1] | display::segment::POINT,
+                quality_buf[0],
+                quality_buf[1],
+            ],
+            (_, 3 | 4) => {
+                sides_buf = Some(if quality_len == 4 {
+                    quality_buf
+                } else {
+                    [display::symbol::DELTA, quality_buf[0], quality_buf[1], quality_buf[2]]
+                });
+                let mut buf = [0u8; 4];
+                buf[..quantity_len].copy_from_slice(&quantity_buf[..quantity_len]);
+                buf[quantity_len] = display::symbol::DELTA;
+                buf.rotate_right(3 - quantity_len);
+                buf
+            }
+            _ => panic!()
+        };
+        (dice_buf, sides_buf)
+    }
+
+    /// Adds the slides of a group of dice rendered by `render_group`. Longer side counts take two slides.
+    fn push_group(slides: &mut Slideshow, (dice_buf, sides_buf): (Buffer, Option<Buffer>), ticks: u16) {
+        match sides_buf {
+            Some(sides_buf) => {
+                slides.push(dice_buf, Self::SETTINGS_HALF_TICKS);
+                slides.push(sides_buf, Self::SETTINGS_HALF_TICKS);
+            }
+            None => slides.push(dice_buf, ticks),
+        }
+    }
+
+    /// Uses the latest aggregated measurements of the accelerometer axes to trigger transitions
+    /// between the "Rolling" and "Displaying" states. In the "Setup" state, a shake opens or closes
+    /// the page of the settings menu instead.
+    pub fn test_acceleration(&mut self) {
+        let amps = (self.acc_x.l2.amplitude_full(), self.acc_y.l2.amplitude_full(), self.acc_z.l2.amplitude_full());
+        let (ax, ay, az) = if let (Some(ax), Some(ay), Some(az)) = amps {
+            (ax, ay, az)
+        } else {
+            return;
+        };
+
+        match &mut self.state {
+            State::Displaying { disturbed_ticks, idle_ticks, .. } => {
+                if Self::acc_has_been_balanced(ax, ay, az, &self.thresholds) {
+                    // the signal amplitudes of all axes have been low, reset the disturbance counter
+                    *disturbed_ticks = 0;
+                    *idle_ticks += 1;
+                    if *idle_ticks > self.thresholds.ticks_to_sleep {
+                        self.enter_sleeping();
+                    }
+                    return;
+                }
+
+                *disturbed_ticks += 1;
+                *idle_ticks = 0;
+                if *disturbed_ticks > self.thresholds.ticks_to_disturb {
+                    // the signal amplitude of at least one axis has been high for long enough time
+                    // to trigger a transition to the "Rolling" state.
+                    // Abort if settings haven't been initialized yet.
+                    if let (Some(quantity), Some(quality)) = (self.quantity, self.quality) {
+                        self.enter_rolling(quantity.value, self.sides(quality));
+                    }
+                }
+            }
+            State::Rolling { balanced_ticks, mode, throws, harvested, starved_ticks, .. } => {
+                let fresh = *harvested != self.entropy.harvested();
+                *harvested = self.entropy.harvested();
+                if Self::acc_has_been_disturbed(ax, ay, az, &self.thresholds) {
+                    // the signal amplitude of at least one axis has been high recently, reset the balance counter.
+                    *balanced_ticks = 0;
+                    *starved_ticks = 0;
+                    return;
+                }
+
+                // the dice may still be waiting for fresh bits long after the device has come to rest
+                *balanced_ticks = balanced_ticks.saturating_add(1);
+                *starved_ticks = if fresh { 0 } else { starved_ticks.saturating_add(1) };
+                if *balanced_ticks >= self.thresholds.ticks_to_balance {
+                    // the signal amplitudes of all axes have been low for long enough time to exit
+                    // the "Rolling" state and display the result. If the result is not ready, try this
+                    // again on the next timer tick.
+                    if self.health.iter().any(|h| h.failure().is_some()) {
+                        // the accelerometer stopped producing noise, the dice may be predictable
+                        self.last_roll = None;
+                        self.show_failure();
+                    } else if let Some(roll) = Roll::new(throws, *mode, self.modifier) {
+                        self.last_roll = Some(roll);
+
+                        #[cfg(feature = "debug_spi")]
+                        self.debug_send(Message::Result { sum: roll.sum, modifier: roll.modifier, dice: roll.dice, count: roll.count });
+
+                        self.show_roll(&roll);
+                    } else if *starved_ticks >= Self::TICKS_TO_STARVE {
+                        // no bits to finish the dice with
+                        self.last_roll = None;
+                        self.show_failure();
+                    }
+                }
+            }
+            State::Sleeping { disturbed_ticks, .. } => {
+                if Self::acc_has_been_balanced(ax, ay, az, &self.thresholds) {
+                    // the signal amplitudes of all axes have been low, reset the disturbance counter
+                    *disturbed_ticks = 0;
+                    return;
+                }
+
+                *disturbed_ticks += 1;
+                if *disturbed_ticks > self.thresholds.ticks_to_wake {
+                    // the signal amplitude of at least one axis has been high for long enough time
+                    // to trigger a transition to the "Rolling" state.
+                    // Abort if settings haven't been initialized yet.
+                    if self.setup {
+                        self.enter_setup();
+                    } else if let (Some(quantity), Some(quality)) = (self.quantity, self.quality) {
+                        self.enter_rolling(quantity.value, self.sides(quality));
+                    }
+                }
+            }
+            State::Setup { menu, shaken_ticks, idle_ticks } => {
+                if Self::acc_has_been_balanced(ax, ay, az, &self.thresholds) {
+                    *shaken_ticks = 0;
+                    *idle_ticks += 1;
+                    if *idle_ticks > self.thresholds.ticks_to_sleep {
+                        self.enter_sleeping();
+                    }
+                    return;
+                }
+
+                *shaken_ticks = shaken_ticks.saturating_add(1);
+                *idle_ticks = 0;
+                // a shake that would start a roll opens or closes the page once
+                if *shaken_ticks != self.thresholds.ticks_to_disturb + 1 {
+                    return;
+                }
+                let mut menu = *menu;
+                let position = self.pot_quality.avg_full::<u16>().unwrap_or(0);
+                match menu.close(position) {
+                    Some(setting) => self.apply(setting),
+                    None => menu.open(position),
+                }
+                if let State::Setup { menu: current, .. } = &mut self.state {
+                    *current = menu;
+                }
+                self.render_menu(menu);
+            }
+        };
+    }
+
+    /// shortcut for checking for sufficient disturbance on any axis
+    fn acc_has_been_disturbed(ax: u16, ay: u16, az: u16, thresholds: &Thresholds) -> bool {
+        let min = thresholds.min_force_amplitude;
+        ax >= min || ay >= min || az >= min
+    }
+
+    /// shortcut for checking for balance on all axis
+    fn acc_has_been_balanced(ax: u16, ay: u16, az: u16, thresholds: &Thresholds) -> bool {
+        let min = thresholds.min_force_amplitude;
+        ax < min && ay < min && az < min
+    }
+
+    /// Displays the error glyph instead of the result of a roll whose entropy failed the health tests or ran out.
+    fn show_failure(&mut self) {
+        self.display.buffer = Self::HEALTH_FAILURE;
+        self.enter_displaying();
+    }
+
+    /// Displays the total of the roll, with the dots of all the displays lit if any of the dice has exploded.
+    /// The number of successes is preceded by the bars. A critical roll is animated before the total if enabled.
+    /// After a roll of mixed dice, pages through every group of dice followed by its subtotal before returning
+    /// to the total. Otherwise, if enabled, pages through every die of the roll. Otherwise, if one
+    /// of two dice has been dropped, e.g. after an advantage roll, briefly shows both dice, or briefly shows
+    /// the dropped dice if enabled, two at a time.
+    fn show_roll(&mut self, roll: &Roll) {
+        self.display.set_number(roll.total());
+        let successes = matches!(roll.mode, Mode::Successes { .. });
+        if successes {
+            // there are at most 20 successes or botches, the first display is always free
+            self.display.buffer[0] = display::symbol::BARS;
+        }
+        if roll.exploded != 0 {
+            for symbol in self.display.buffer.iter_mut() {
+                *symbol |= display::segment::POINT;
+            }
+        }
+        self.enter_displaying();
+
+        let total = self.display.buffer;
+        let State::Displaying { slides, critical, .. } = &mut self.state else {
+            return;
+        };
+
+        if let Some(success) = roll.critical().filter(|_| self.show_critical) {
+            let mut animation = Critical::new(success, total);
+            // the total doesn't appear before the animation
+            animation.advance(&mut self.display.buffer);
+            *critical = Some(animation);
+        }
+
+        if roll.group_count > 1 {
+            slides.push(total, Self::ROLL_KEPT_TICKS);
+            for (group, subtotal) in roll.groups().iter().zip(roll.subtotals()) {
+                Self::push_group(slides, Self::render_group(group.quantity, group.sides), Self::ROLL_GROUP_TICKS);
+                let mut subtotal_buf = [0u8; 4];
+                let subtotal_len = display::encode_i16_into(&mut subtotal_buf, subtotal, false);
+                subtotal_buf.rotate_right(4 - subtotal_len);
+                if successes {
+                    subtotal_buf[0] = display::symbol::BARS;
+                }
+                slides.push(subtotal_buf, Self::ROLL_DICE_TICKS);
+            }
+        } else if self.show_dice && roll.count > 1 {
+            slides.push(total, Self::ROLL_KEPT_TICKS);
+            // the index of the die followed by its value, e.g. "2. 5". The index of a value it doesn't fit along,
+            // e.g. "12." before "872", is shown on its own first
+            for (i, d) in roll.dice().iter().enumerate() {
+                let index = i as u16 + 1;
+                let page = if roll.fudge {
+                    let mut page = Self::render_pair(index, None);
+                    page[3] = Self::fudge_face(*d);
+                    page
+                } else {
+                    if !Self::pair_fits(index, *d) {
+                        slides.push(Self::render_pair(index, None), Self::ROLL_INDEX_TICKS);
+                    }
+                    Self::render_pair(index, Some(*d))
+                };
+                slides.push(page, Self::ROLL_DICE_TICKS);
+            }
+        } else if successes || roll.dropped_dice().next().is_none() {
+            // nothing else to show
+        } else if roll.count == 2 {
+            slides.push(total, Self::ROLL_KEPT_TICKS);
+            slides.push(Self::render_dice(roll, roll.dice[0], Some(roll.dice[1])), Self::ROLL_DICE_TICKS);
+        } else if self.show_dropped {
+            slides.push(total, Self::ROLL_KEPT_TICKS);
+            // the total takes the first and the last slides, dice that don't fit aren't shown
+            let mut dropped = roll.dropped_dice().take((Slideshow::MAX_SLIDES - 2) * 2);
+            while let Some(left) = dropped.next() {
+                slides.push(Self::render_dice(roll, left, dropped.next()), Self::ROLL_DICE_TICKS);
+            }
+        }
+
+        // the slides end with the total
+        if !slides.is_empty() {
+            slides.push(total, 0);
+        }
+    }
+
+    /// Renders up to two dice of the roll side by side, the left one followed by a dot.
+    fn render_dice(roll: &Roll, left: u16, right: Option<u16>) -> Buffer {
+        if !roll.fudge {
+            return Self::render_pair(left, right);
+        }
+        [0, Self::fudge_face(left) | display::segment::POINT, 0, right.map_or(0, Self::fudge_face)]
+    }
+
+    /// Returns the face of a Fudge die: a minus, a blank or a plus.
+    fn fudge_face(die: u16) -> u8 {
+        match die {
+            1 => display::symbol::MINUS,
+            3 => display::symbol::PLUS,
+            _ => 0,
+        }
+    }
+
+    /// Returns whether two numbers fit on the display side by side.
+    fn pair_fits(left: u16, right: u16) -> bool {
+        let mut buf = [0u8; 4];
+        display::encode_u16_into(&mut buf, left) + display::encode_u16_into(&mut buf, right) <= 4
+    }
+
+    /// Renders up to two numbers side by side, the left one followed by a dot. Each number takes
+    /// 2 displays unless the other one leaves room for more digits. If the numbers don't fit together,
+    /// only the right one is rendered.
+    fn render_pair(left: u16, right: Option<u16>) -> Buffer {
+        let mut left_buf = [0u8; 4];
+        let left_len = display::encode_u16_into(&mut left_buf, left);
+        let mut right_buf = [0u8; 4];
+        let right_len = right.map_or(0, |right| display::encode_u16_into(&mut right_buf, right));
+
+        let mut buffer = [0u8; 4];
+        buffer[4 - right_len..].copy_from_slice(&right_buf[..right_len]);
+        if left_len + right_len > 4 {
+            return buffer;
+        }
+
+        let left_width = if right_len == 3 { 1 } else { left_len.max(2) };
+        buffer[left_width - left_len..left_width].copy_from_slice(&left_buf[..left_len]);
+        buffer[left_width - 1] |= display::segment::POINT;
+        buffer
+    }
+
+    /// Transitions the device into the "Rolling" state and prepares parameters for the random
+    /// result generation from the current settings. Returns false and shows the error glyph instead
+    /// if the pool no longer fits along the selected dice.
+    fn enter_rolling(&mut self, quantity: u8, quality: u16) -> bool {
+        if !self.pool_fits(quantity, quality) {
+            self.show_failure();
+            return false;
+        }
+        if matches!(self.state, State::Sleeping { .. }) {
+            self.timer.set_rate(TickRate::Normal);
+        }
+        // only the failures during the roll count, a source that is still unhealthy fails again right away
+        for health in &mut self.health {
+            health.clear();
+        }
+        let (mode, quantity) = match quantity {
+            ADVANTAGE => (Mode::KeepHighest(1), 2),
+            DISADVANTAGE => (Mode::KeepLowest(1), 2),
+            _ => (self.mode, quantity),
+        };
+        let mut program = Program { mode, exploding: self.exploding, reroll: self.reroll, modifier: self.modifier, ..Program::new() };
+        program.add(Group { quantity, sides: quality });
+        if Self::pooled(quantity, quality) {
+            for group in self.pool() {
+                program.add(*group);
+            }
+        }
+        let (throws, params) = program.start();
+        self.state = State::Rolling {
+            mode,
+            params,
+            throws,
+            balanced_ticks: 0,
+            harvested: self.entropy.harvested(),
+            starved_ticks: 0,
+            animation: Spinner::new(),
+        };
+
+        #[cfg(feature = "debug_spi")]
+        self.debug_send(Message::State(self.state.id()));
+        true
+    }
+
+    /// Transitions the device into the "Displaying" state.
+    fn enter_displaying(&mut self) {
+        if matches!(self.state, State::Sleeping { .. }) {
+            self.timer.set_rate(TickRate::Normal);
+        }
+        self.state = State::Displaying { disturbed_ticks: 0, idle_ticks: 0, slides: Slideshow::new(), critical: None };
+
+        #[cfg(feature = "debug_spi")]
+        self.debug_send(Message::State(self.state.id()));
+    }
+
+    /// Transitions the device into the "Setup" state on the page of the menu the quality knob is on.
+    fn enter_setup(&mut self) {
+        if matches!(self.state, State::Sleeping { .. }) {
+            self.timer.set_rate(TickRate::Normal);
+        }
+        let menu = Menu::new(self.pot_quality.avg_full::<u16>().unwrap_or(0));
+        // the shake in progress, e.g. the one that has woken the device up, doesn't open the page
+        self.state = State::Setup { menu, shaken_ticks: u8::MAX, idle_ticks: 0 };
+
+        #[cfg(feature = "debug_spi")]
+        self.debug_send(Message::State(self.state.id()));
+
+        self.render_menu(menu);
+    }
+
+    /// Transitions the device into the "Sleeping" state.
+    fn enter_sleeping(&mut self) {
+        self.timer.set_rate(TickRate::Sleeping);
+        self.state = State::Sleeping { disturbed_ticks: 0, animation: BlinkingDot::new() };
+
+        #[cfg(feature = "debug_spi")]
+        self.debug_send(Message::State(self.state.id()));
+
+        // turn the display off immediately
+        self.display.force_output(&mut self.port, 0, 0);
+    }
+
+    /// Interrupt handler for the timer.
+    pub fn timer_interrupt(&mut self) {
+        // every tick of a roll draws a fresh number once the generator has been reseeded with enough fresh bits,
+        // otherwise the throw waits for the next tick. The dice are kept once they are complete and the device
+        // has come to rest.
+        let drawing = matches!(&self.state, State::Rolling { throws, balanced_ticks, .. }
+            if !throws.is_complete() || *balanced_ticks == 0);
+        let draw = drawing.then(|| {
+            self.rng.reseed(&mut self.entropy).then(|| self.rng.next_u32())
+        }).flatten();
+
+        match &mut self.state {
+            State::Rolling { animation: spinner, throws, params, .. } => {
+                // advance the spinning animation.
+                spinner.advance(&mut self.display.buffer);
+
+                // generate the a new random die throw from the highest bits and add to the results on success.
+                // The bound is the number of sides of the die.
+                if let Some(rnd) = draw.and_then(|draw| random::generate(&params[throws.group()], (draw >> 16) as u16)) {
+                    throws.put(rnd + 1);
+                }
+            }
+            State::Sleeping { animation, .. } => {
+                animation.advance(&mut self.display, &mut self.port);
+            }
+            State::Displaying { slides, critical, .. } => {
+                // the slides start once the critical roll animation is over
+                match critical {
+                    Some(animation) => {
+                        if animation.advance(&mut self.display.buffer) {
+                            *critical = None;
+                        }
+                    }
+                    None => slides.advance(&mut self.display.buffer),
+                }
+            }
+            State::Setup { .. } => {}
+        }
+
+        #[cfg(feature = "debug_spi")]
+        if let Some(draw) = draw {
+            self.debug_send(Message::Entropy((draw >> 16) as u16));
+        }
+
+        if !matches!(self.state, State::Sleeping{ .. } ) {
+            self.display.refresh(&mut self.port);
+        }
+
+        self.adc_start(Measurement::PotQuantity);
+    }
+
+    /// Interrupt handler for the ADC. Accepts the result of the conversion started by the last `adc_start`.
+    pub fn adc_interrupt(&mut self, result: u16) {
+        if let Some(m) = self.adc_measuring.take() {
+            self.adc_ready(m, result);
+        }
+    }
+
+    /// Starts the specified measurement on the ADC.
+    fn adc_start(&mut self, m: Measurement) {
+        if self.adc_measuring.is_some() {
+            // Currently, the ~5ms interval between timer ticks leaves enough time for 5 ADC measurements
+            // and their interpretation. If the code changes and we start seeing panics here,
+            // we'll know that something needs to be optimized.
+            panic!();
+        }
+
+        self.sampler.start(&m);
+        self.adc_measuring = Some(m);
+    }
+
+    /// Handles a completed measurement result from the ADC.
+    fn adc_ready(&mut self, m: Measurement, result: u16) {
+        #[cfg(any(feature = "debug_spi", feature = "console"))]
+        {
+            self.measurements[m as usize] = result;
+        }
+
+        // if this is an accelerometer measurement, test its health and whiten it into the entropy pool.
+        // A healthy axis at rest can read the same for seconds, only the measurements taken while the dice
+        // are being shaken are tested.
+        if matches!(m, Measurement::AccX | Measurement::AccY | Measurement::AccZ) {
+            let axis = m as usize - Measurement::AccX as usize;
+            if matches!(self.state, State::Rolling { balanced_ticks: 0, .. }) {
+                self.health[axis].put(result);
+            }
+            self.entropy.put(axis, result);
+        }
+
+        match m {
+            Measurement::PotQuantity => {
+                self.pot_quantity.put(result);
+                self.adc_start(Measurement::PotQuality);
+            }
+            Measurement::PotQuality => {
+                self.pot_quality.put(result);
+                self.adc_start(Measurement::AccX);
+            }
+            Measurement::AccX => {
+                self.acc_x.put(result);
+
+                self.adc_start(Measurement::AccY);
+            }
+            Measurement::AccY => {
+                self.acc_y.put(result);
+
+                self.adc_start(Measurement::AccZ);
+            }
+            Measurement::AccZ => {
+                self.acc_z.put(result);
+
+                self.sampler.stop();
+
+                #[cfg(feature = "debug_spi")]
+                self.debug_measurements();
+
+                self.test_pots();
+                self.test_acceleration();
+            }
+        }
+    }
+
+    /// Sends the results of all the measurements taken during the current tick over the debug channel.
+    #[cfg(feature = "debug_spi")]
+    fn debug_measurements(&mut self) {
+        let [quantity, quality, x, y, z] = self.measurements;
+        self.debug_send(Message::Pots([quantity, quality]));
+        self.debug_send(Message::Acc([x, y, z]));
+    }
+
+    /// Queues the message to be sent over the debug channel. The message is dropped whole if the channel can't
+    /// take all of its frame, the receiver resynchronises on the next frame.
+    #[cfg(feature = "debug_spi")]
+    fn debug_send(&mut self, message: Message) {
+        let mut frame = [0; protocol::MAX_FRAME];
+        let size = message.encode(&mut frame);
+        if self.debug_buf.free() < size {
+            return;
+        }
+        for b in &frame[..size] {
+            self.debug_buf.write(*b);
+        }
+    }
+
+    /// Handles a byte received by the serial console.
+    #[cfg(feature = "console")]
+    pub fn console_receive(&mut self, byte: u8) {
+        let result = match self.console.receive(byte) {
+            Some(Ok(command)) => self.console_execute(command),
+            Some(Err(e)) => Err(e),
+            None => Ok(()),
+        };
+        if let Err(e) = result {
+            self.console.error(e);
+        }
+    }
+
+    /// Executes a console command and queues the reply.
+    #[cfg(feature = "console")]
+    fn console_execute(&mut self, command: Command) -> Result<(), Error> {
+        match command {
+            Command::Help => {
+                for (i, name) in COMMANDS.iter().enumerate() {
+                    if i > 0 {
+                        self.console.text(" ");
+                    }
+                    self.console.text(name);
+                }
+                self.console.end();
+            }
+            Command::State => {
+                self.console.text(self.state.id().name()).end();
+            }
+            Command::Settings => {
+                let (Some(quantity), Some(quality)) = (self.quantity, self.quality) else {
+                    return Err(Error::NotReady);
+                };
+                let sides = self.sides(quality);
+                self.console.quantity(quantity.value).text("d").sides(sides);
+                if Self::pooled(quantity.value, sides) {
+                    for group in &self.pool[..self.pool_len as usize] {
+                        self.console.text("+").number(group.quantity as u16).text("d").sides(group.sides);
+                    }
+                }
+                if self.exploding {
+                    self.console.text("!");
+                }
+                self.console.reroll(self.reroll);
+                let mode = if matches!(quantity.value, ADVANTAGE | DISADVANTAGE) { Mode::Sum } else { self.mode };
+                self.console.mode(mode);
+                if self.modifier != 0 && !matches!(mode, Mode::Successes { .. }) {
+                    self.console.modifier(self.modifier);
+                }
+                if self.settings_overridden {
+                    self.console.text(" (override)");
+                }
+                self.console.end();
+            }
+            Command::Last => match self.last_roll {
+                Some(roll) => {
+                    self.console.integer(roll.total()).text(" =");
+                    for (i, d) in roll.dice().iter().enumerate() {
+                        // dropped dice are put in parentheses
+                        let kept = roll.is_kept(i);
+                        self.console.text(if kept { " " } else { " (" });
+                        if roll.fudge {
+                            self.console.fudge(*d);
+                        } else {
+                            self.console.number(*d);
+                        }
+                        if !kept {
+                            self.console.text(")");
+                        }
+                        // exploded dice are marked with an exclamation mark
+                        if roll.is_exploded(i) {
+                            self.console.text("!");
+                        }
+                    }
+                    if roll.modifier != 0 {
+                        self.console.text(" ").modifier(roll.modifier);
+                    }
+                    // the subtotals of mixed dice follow, e.g. "(2d6: 7, 1d8: 5)"
+                    if roll.group_count > 1 {
+                        for (i, (group, subtotal)) in roll.groups().iter().zip(roll.subtotals()).enumerate() {
+                            self.console
+                                .text(if i == 0 { " (" } else { ", " })
+                                .number(group.quantity as u16).text("d").sides(group.sides).text(": ")
+                                .integer(subtotal);
+                        }
+                        self.console.text(")");
+                    }
+                    self.console.end();
+                }
+                None => self.console.text("none").end(),
+            },
+            Command::Adc => {
+                let [quantity, quality, x, y, z] = self.measurements;
+                self.console
+                    .text("quantity ").number(quantity)
+                    .text(" quality ").number(quality)
+                    .text(" x ").number(x)
+                    .text(" y ").number(y)
+                    .text(" z ").number(z)
+                    .end();
+            }
+            Command::Entropy => {
+                self.console
+                    .text("queued ").number(self.entropy.available() as u16)
+                    .text(" harvested ").number(self.entropy.harvested())
+                    .text(" consumed ").number(self.entropy.consumed())
+                    .end();
+            }
+            Command::Roll => {
+                let (Some(quantity), Some(quality)) = (self.quantity, self.quality) else {
+                    return Err(Error::NotReady);
+                };
+                if !self.enter_rolling(quantity.value, self.sides(quality)) {
+                    return Err(Error::TooManyDice);
+                }
+                self.console.text("ok").end();
+            }
+            Command::Set(Some((quantity, sides))) => {
+                if !self.select(quantity, sides) {
+                    return Err(Error::InvalidArgument);
+                }
+                self.show_settings();
+                self.console.text("ok").end();
+            }
+            Command::Preset(program) => {
+                if !self.load(&program) {
+                    return Err(Error::InvalidArgument);
+                }
+                self.console.text("ok").end();
+            }
+            Command::Set(None) => {
+                // the knob positions get detected from scratch on the next tick
+                self.settings_overridden = false;
+                self.quantity = None;
+                self.setup = false;
+                self.quality = None;
+                self.console.text("ok").end();
+            }
+            Command::Sides(sides) => {
+                if !(2..=MAX_SIDES).contains(&sides) {
+                    return Err(Error::InvalidArgument);
+                }
+                self.set_custom_sides(sides);
+                self.console.text("ok").end();
+            }
+            Command::Pool(Some(group)) => {
+                if !self.add_to_pool(group) {
+                    return Err(Error::InvalidArgument);
+                }
+                self.console.text("ok").end();
+            }
+            Command::Pool(None) => {
+                self.clear_pool();
+                self.console.text("ok").end();
+            }
+            Command::Modifier(modifier) => {
+                if modifier.unsigned_abs() > MAX_MODIFIER as u8 {
+                    return Err(Error::InvalidArgument);
+                }
+                self.set_modifier(modifier);
+                self.console.text("ok").end();
+            }
+            Command::Keep(mode) | Command::Count(mode) => {
+                self.set_mode(mode);
+                self.console.text("ok").end();
+            }
+            Command::Dropped(show) => {
+                self.set_show_dropped(show);
+                self.console.text("ok").end();
+            }
+            Command::Reroll(reroll) => {
+                self.set_reroll(reroll);
+                self.console.text("ok").end();
+            }
+            Command::Critical(show) => {
+                self.set_show_critical(show);
+                self.console.text("ok").end();
+            }
+            Command::Dice(show) => {
+                self.set_show_dice(show);
+                self.console.text("ok").end();
+            }
+            Command::Explode(exploding) => {
+                self.set_exploding(exploding);
+                self.console.text("ok").end();
+            }
+            Command::Threshold(None) => {
+                for threshold in Threshold::ALL {
+                    self.console.text(threshold.name()).text(" ").number(threshold.get(&self.thresholds)).end();
+                }
+            }
+            Command::Threshold(Some((threshold, value))) => {
+                threshold.set(&mut self.thresholds, value)?;
+                self.console.text("ok").end();
+            }
+        }
+        Ok(())
+    }
+}
@@ -1,5 +1,5 @@
-use avr_device::atmega328p::Peripherals;
 use crate::display::segment::POINT;
+use crate::hardware::DisplayPort;
 
 /// Maps segments of a standard 7-segment display to channel bits of the I/O port "D" to which
 /// the corresponding LED's are connected on the board.
@@ -29,8 +29,24 @@ pub mod symbol {
     pub const EIGHT: u8 = A | B | C | D | E | F | G;
     pub const NINE: u8 = A | B | C | D | F | G;
     pub const ALPHA: u8 = A | B | C | E | F | G;
+    pub const CHARLIE: u8 = A | D | E | F;
     pub const DELTA: u8 = B | C | D | E | G;
     pub const ECHO: u8 = A | D | E | F | G;
+    pub const FOXTROT: u8 = A | E | F | G;
+    pub const HOTEL: u8 = B | C | E | F | G;
+    pub const LIMA: u8 = D | E | F;
+    /// Lowercase "n".
+    pub const NOVEMBER: u8 = C | E | G;
+    /// Lowercase "o".
+    pub const OSCAR: u8 = C | D | E | G;
+    pub const PAPA: u8 = A | B | E | F | G;
+    /// Lowercase "r".
+    pub const ROMEO: u8 = E | G;
+    pub const MINUS: u8 = G;
+    /// Approximation of a plus sign: the left vertical bar crossed by the middle segment.
+    pub const PLUS: u8 = E | F | G;
+    /// Three horizontal bars, mark a number of successes rather than a sum.
+    pub const BARS: u8 = A | D | G;
 
     /// Defines an array where visual representations of digits 0-9 are stored under the corresponding indices.
     pub const MAP: [u8; 10] = [ZERO, ONE, TWO, THREE, FOUR, FIVE, SIX, SEVEN, EIGHT, NINE];
@@ -75,26 +91,10 @@ impl Display {
         }
     }
 
-    /// Prepares I/O ports "B" and "D" for the operation of the display.
-    pub fn initialize(&mut self) {
-        unsafe {
-            let p = Peripherals::steal();
-            // Switch all channels of the I/O port "D" into the output mode.
-            p.PORTD.ddrd.write(|w| w.bits(0xff));
-            // Set outputs of all the I/O port "D" channels to LOW - no segment is active.
-            p.PORTD.portd.write(|w| w.bits(0));
-
-            // Switch channels of the I/O port "B" connected to the displays into the output mode.
-            p.PORTB.ddrb.modify(|r, w| w.bits(r.bits() | position::MASK_ALL));
-            // Set outputs of the I/O port "B" channels connected to the displays to LOW — no display is selected.
-            p.PORTB.portb.modify(|r, w| w.bits(r.bits() & !position::MASK_ALL));
-        }
-    }
-
     /// Switch active display to the next one.
     ///
     /// This is intended to be called at regular intervals by the timer interrupt handler.
-    pub fn refresh(&mut self) {
+    pub fn refresh<P: DisplayPort>(&mut self, port: &mut P) {
         for n in 1..=self.buffer.len() {
             if self.buffer[self.next_index] == 0 {
                 self.next_index = (self.next_index + 1) % 4;
@@ -104,45 +104,31 @@ impl Display {
             }
         }
 
-        unsafe {
-            let p = Peripherals::steal();
-            // Turn off all segments on the currently active display.
-            p.PORTD.portd.write(|w| w.bits(0));
-            // Unset all channel bits connected to the displays, set the bit of the display that must be activated next.
-            p.PORTB.portb.modify(|r, w| w.bits((r.bits() & !position::MASK_ALL) | position::MAP[self.next_index]));
-            // Copy the corresponding element from the diplay buffer into the I/O port "D".
-            p.PORTD.portd.write(|w| w.bits(self.buffer[self.next_index]));
-        };
+        port.output(self.buffer[self.next_index], position::MAP[self.next_index]);
 
         self.next_index = (self.next_index + 1) % 4;
     }
 
-    /// Renders the provided unsigned 16-bit number aligned to the right with a dot at the end,
-    /// writes the result to the display buffer.
-    pub fn set_number(&mut self, n: u16) {
-        let mut tmp = [0u8; 4];
-        let size = encode_u16_into(&mut tmp, n);
-        let shift = 4 - size;
-
-        for i in 0..shift {
-            self.buffer[i] = 0;
-        }
-
-        for i in 0..size {
-            self.buffer[i + shift] = tmp[i];
-        }
+    /// Renders the provided signed 16-bit number aligned to the right with a dot at the end,
+    /// writes the result to the display buffer. Numbers below -999 are rendered as -999, numbers above 9999
+    /// as 9999.
+    pub fn set_number(&mut self, n: i16) {
+        let size = encode_i16_into(&mut self.buffer, n.clamp(-999, 9999), false);
+        self.buffer.rotate_right(4 - size);
 
         self.buffer[self.buffer.len() - 1] |= POINT;
     }
 
     /// Makes the display show the specified symbol in the specified position regardless of
     /// the current internal state.
-    pub fn force_output(&mut self, symbol: u8, position: u8) {
-        unsafe {
-            let p = Peripherals::steal();
-            p.PORTB.portb.modify(|r, w| w.bits((r.bits() & !position::MASK_ALL) | position));
-            p.PORTD.portd.write(|w| w.bits(symbol));
-        };
+    pub fn force_output<P: DisplayPort>(&mut self, port: &mut P, symbol: u8, position: u8) {
+        port.output(symbol, position);
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Self::new()
     }
 }
 
@@ -156,7 +142,7 @@ pub fn encode_u16_into(buf: &mut [u8], mut n: u16) -> usize {
             buf[size] = symbol::MAP[d as usize];
             size += 1;
         }
-        n = n % divisor;
+        n %= divisor;
         divisor /= 10;
     }
 
@@ -165,13 +151,23 @@ pub fn encode_u16_into(buf: &mut [u8], mut n: u16) -> usize {
         size = 1;
     }
 
-    for i in size..buf.len() {
-        buf[i] = 0;
-    }
+    buf[size..].fill(0);
 
     size
 }
 
+/// Renders a signed 16-bit number into the provided buffer. Negative numbers are preceded by a minus,
+/// positive ones are preceded by a plus if `plus` is set. Returns the number of symbols rendered.
+pub fn encode_i16_into(buf: &mut [u8], n: i16, plus: bool) -> usize {
+    let sign = match n {
+        _ if n < 0 => symbol::MINUS,
+        _ if n > 0 && plus => symbol::PLUS,
+        _ => return encode_u16_into(buf, n as u16),
+    };
+    buf[0] = sign;
+    1 + encode_u16_into(&mut buf[1..], n.unsigned_abs())
+}
+
 /// Renders an unsigned 8-bit number into the provided buffer. Returns the number of digits rendered.
 pub fn encode_u8_into(buf: &mut [u8], mut n: u8) -> usize {
     let mut size = 0;
@@ -182,7 +178,7 @@ pub fn encode_u8_into(buf: &mut [u8], mut n: u8) -> usize {
             buf[size] = symbol::MAP[d as usize];
             size += 1;
         }
-        n = n % divisor;
+        n %= divisor;
         divisor /= 10;
     }
 
@@ -191,20 +187,7 @@ pub fn encode_u8_into(buf: &mut [u8], mut n: u8) -> usize {
         size = 1;
     }
 
-    for i in size..buf.len() {
-        buf[i] = 0;
-    }
+    buf[size..].fill(0);
 
     size
 }
-
-/// Re-initializes display from scratch and makes all displays show the specified symbol.
-pub fn fail_with_symbol(s: u8) {
-    unsafe {
-        let p = Peripherals::steal();
-        p.PORTD.ddrd.write(|w| w.bits(0xff));
-        p.PORTD.portd.write(|w| w.bits(s));
-        p.PORTB.ddrb.modify(|r, w| w.bits(r.bits() | position::MASK_ALL));
-        p.PORTB.portb.write(|w| w.bits(position::MASK_ALL));
-    }
-}
@@ -0,0 +1,228 @@
+//! Entropy collected from the accelerometer and the generator of the random numbers the dice are thrown with.
+//!
+//! The lowest bits of the accelerometer measurements are whitened into a queue of unbiased bits by the [`Pool`].
+//! The [`Generator`] is a xoshiro128** that takes fresh bits from the queue before every draw, so every die is
+//! thrown with new entropy, and a throw waits until enough bits have been harvested.
+//!
+//! The samples of every axis taken while the dice are shaken also go through the [`Health`] tests, which detect
+//! an accelerometer that stopped producing noise before the dice become predictable.
+
+/// Whitens the accelerometer measurements into a queue of unbiased bits.
+///
+/// Only the lowest bit of a measurement carries noise, the rest is mostly gravity. The lowest bits of every axis
+/// are paired up and debiased with the Von Neumann extractor: a pair of different bits yields the first one, a
+/// pair of equal bits yields nothing.
+pub struct Pool {
+    /// The lowest bit of the unpaired measurement of every axis, if any.
+    pending: [Option<bool>; 3],
+    /// The queued bits, the oldest one in the lowest bit.
+    bits: u64,
+    len: u8,
+    /// How many bits have been queued and taken from the queue, wrapping around.
+    harvested: u16,
+    consumed: u16,
+}
+
+impl Pool {
+    /// How many bits the queue holds, the bits extracted while it's full are dropped.
+    pub const CAPACITY: u8 = 64;
+
+    /// Returns a new empty pool.
+    pub const fn new() -> Self {
+        Self { pending: [None; 3], bits: 0, len: 0, harvested: 0, consumed: 0 }
+    }
+
+    /// Pairs the lowest bit of the measurement of the axis with the previous one, queueing the extracted bit.
+    pub fn put(&mut self, axis: usize, measurement: u16) {
+        let bit = measurement & 1 != 0;
+        let Some(first) = self.pending[axis].take() else {
+            self.pending[axis] = Some(bit);
+            return;
+        };
+        if first != bit && self.len < Self::CAPACITY {
+            self.bits |= (first as u64) << self.len;
+            self.len += 1;
+            self.harvested = self.harvested.wrapping_add(1);
+        }
+    }
+
+    /// Returns how many bits are queued.
+    pub fn available(&self) -> u8 {
+        self.len
+    }
+
+    /// Takes the specified number of the oldest bits, at most 32, if that many are queued.
+    pub fn take(&mut self, count: u8) -> Option<u32> {
+        if count > self.len {
+            return None;
+        }
+        let bits = (self.bits & ((1u64 << count) - 1)) as u32;
+        self.bits >>= count;
+        self.len -= count;
+        self.consumed = self.consumed.wrapping_add(count as u16);
+        Some(bits)
+    }
+
+    /// Returns how many bits have been queued since the start, wrapping around.
+    pub fn harvested(&self) -> u16 {
+        self.harvested
+    }
+
+    /// Returns how many bits have been taken from the queue since the start, wrapping around.
+    pub fn consumed(&self) -> u16 {
+        self.consumed
+    }
+}
+
+impl Default for Pool {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The xoshiro128** generator.
+pub struct Generator {
+    state: [u32; 4],
+}
+
+impl Generator {
+    /// The state the device starts with, before any measurements have been taken.
+    const INITIAL: [u32; 4] = [0x9e37_79b9, 0x243f_6a88, 0x85a3_08d3, 0x1319_8a2e];
+
+    /// Returns a generator with the specified state. An all-zero state is replaced with the initial one.
+    pub const fn new(state: [u32; 4]) -> Self {
+        if state[0] | state[1] | state[2] | state[3] == 0 {
+            return Self { state: Self::INITIAL };
+        }
+        Self { state }
+    }
+
+    /// How many fresh bits are mixed into the state before a draw, as many as `random::generate` takes.
+    pub const RESEED_BITS: u8 = 16;
+
+    /// Takes `RESEED_BITS` bits from the pool and mixes them into the state. Returns false and leaves the state
+    /// alone if the pool doesn't have enough bits yet.
+    pub fn reseed(&mut self, pool: &mut Pool) -> bool {
+        let Some(bits) = pool.take(Self::RESEED_BITS) else {
+            return false;
+        };
+        self.state[0] ^= bits;
+        self.state[1] ^= bits.rotate_left(16);
+        if self.state == [0; 4] {
+            self.state = Self::INITIAL;
+        }
+        // spread the bits over the whole state
+        self.next_u32();
+        true
+    }
+
+    /// Returns the next random number and advances the state.
+    pub fn next_u32(&mut self) -> u32 {
+        let s = &mut self.state;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 9;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(11);
+        result
+    }
+}
+
+impl Default for Generator {
+    fn default() -> Self {
+        Self::new(Self::INITIAL)
+    }
+}
+
+/// The health tests that can fail.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Failure {
+    /// The same sample has been repeated too many times in a row, e.g. the axis is disconnected or saturated.
+    Repetition,
+    /// A single value has taken up too much of a window of samples.
+    Proportion,
+}
+
+/// Continuous health tests of the samples of a single accelerometer axis, modelled on NIST SP 800-90B:
+/// the repetition count test and the adaptive proportion test.
+///
+/// The readings of an axis at rest can stay the same for seconds, so the device only tests the samples taken while
+/// it is shaken. Even then an axis the device isn't moved along barely changes, so the cutoffs assume a min-entropy
+/// of only 0.1 bit per sample, with a false positive probability of 2^-20 for both tests.
+pub struct Health {
+    /// The latest sample and how many times in a row it has been repeated.
+    last: u16,
+    repetitions: u16,
+    /// The first sample of the current window, how many samples of the window match it and how many samples
+    /// the window contains so far.
+    first: u16,
+    matches: u16,
+    window: u16,
+    /// Whether the last complete window has failed the adaptive proportion test.
+    disproportionate: bool,
+    /// The test that failed since the last call to `clear`.
+    failure: Option<Failure>,
+}
+
+impl Health {
+    /// The repetition count test fails when a sample is repeated this many times: 1 + 20 / 0.1.
+    pub const REPETITION_CUTOFF: u16 = 201;
+    /// The number of samples in a window of the adaptive proportion test.
+    pub const WINDOW: u16 = 512;
+    /// The adaptive proportion test fails when this many samples of a window match the first one.
+    pub const PROPORTION_CUTOFF: u16 = 502;
+
+    /// Returns new tests that haven't seen any samples.
+    pub const fn new() -> Self {
+        Self { last: 0, repetitions: 0, first: 0, matches: 0, window: 0, disproportionate: false, failure: None }
+    }
+
+    /// Runs both tests on the next sample.
+    pub fn put(&mut self, sample: u16) {
+        if self.repetitions > 0 && sample == self.last {
+            self.repetitions = self.repetitions.saturating_add(1);
+        } else {
+            self.last = sample;
+            self.repetitions = 1;
+        }
+
+        if self.window == 0 {
+            self.first = sample;
+            self.matches = 0;
+        }
+        if sample == self.first {
+            self.matches += 1;
+        }
+        self.window = (self.window + 1) % Self::WINDOW;
+        if self.window == 0 {
+            self.disproportionate = self.matches >= Self::PROPORTION_CUTOFF;
+        }
+
+        // an unhealthy axis keeps failing on every sample: a stuck one until it changes, a biased one until
+        // a whole window passes
+        if self.repetitions >= Self::REPETITION_CUTOFF {
+            self.failure = Some(Failure::Repetition);
+        } else if self.disproportionate || self.matches >= Self::PROPORTION_CUTOFF {
+            self.failure = Some(Failure::Proportion);
+        }
+    }
+
+    /// Returns the test that failed since the last call to `clear`.
+    pub fn failure(&self) -> Option<Failure> {
+        self.failure
+    }
+
+    /// Forgets the failures. Tests keep their counts, so a source that is still unhealthy fails again.
+    pub fn clear(&mut self) {
+        self.failure = None;
+    }
+}
+
+impl Default for Health {
+    fn default() -> Self {
+        Self::new()
+    }
+}
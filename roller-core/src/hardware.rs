@@ -0,0 +1,46 @@
+/// Defines things that we measure with the ADC.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Measurement {
+    PotQuantity,
+    PotQuality,
+    AccX,
+    AccY,
+    AccZ,
+}
+
+/// Defines frequencies at which the timer interrupt handler is expected to be called.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TickRate {
+    /// `Device::NORMAL_FREQUENCY`
+    Normal,
+    /// `Device::SLEEPING_FREQUENCY`
+    Sleeping,
+}
+
+/// Drives the segments and the digit selection lines of the displays.
+pub trait DisplayPort {
+    /// Makes the displays selected by the position bits show the specified symbol, deselecting
+    /// all other displays.
+    fn output(&mut self, symbol: u8, position: u8);
+
+    /// Re-initializes the port from scratch and makes all displays show the specified symbol.
+    ///
+    /// This is intended to be used when the program is in an unrecoverable state.
+    fn fail(&mut self, symbol: u8);
+}
+
+/// Performs analog measurements.
+pub trait Sampler {
+    /// Starts the specified measurement. The result is expected to be passed back
+    /// to `Device::adc_interrupt` once the conversion is complete.
+    fn start(&mut self, m: &Measurement);
+
+    /// Disables the sampler until the next measurement is started.
+    fn stop(&mut self);
+}
+
+/// Controls the frequency of the timer that calls `Device::timer_interrupt`.
+pub trait Timer {
+    /// Switches the timer to the specified tick rate.
+    fn set_rate(&mut self, rate: TickRate);
+}
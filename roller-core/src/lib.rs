@@ -0,0 +1,25 @@
+//! Hardware-independent logic of the roller: settings, roll detection, random result generation
+//! and everything that ends up on the display.
+//!
+//! The firmware crate binds this logic to the actual MCU by implementing the traits from [`hardware`].
+#![no_std]
+
+#[cfg(feature = "mock")]
+extern crate std;
+
+pub mod utils;
+pub mod scales;
+pub mod random;
+pub mod entropy;
+pub mod animation;
+pub mod display;
+pub mod hardware;
+pub mod device;
+pub mod protocol;
+pub mod notation;
+pub mod setup;
+#[cfg(feature = "console")]
+pub mod console;
+
+#[cfg(feature = "mock")]
+pub mod mock;
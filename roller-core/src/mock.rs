@@ -0,0 +1,76 @@
+//! In-memory implementations of the hardware traits that record everything the device does
+//! to them. Intended for tests and host tools.
+
+use std::vec::Vec;
+
+use crate::hardware::{DisplayPort, Measurement, Sampler, TickRate, Timer};
+
+/// Records symbols and positions driven by the device.
+#[derive(Default)]
+pub struct MockDisplay {
+    /// Every (symbol, position) pair output to the port, oldest first.
+    pub outputs: Vec<(u8, u8)>,
+    /// The symbol passed to `fail`, if it was called.
+    pub failed: Option<u8>,
+}
+
+impl MockDisplay {
+    /// Returns the symbol and the position that are currently being output.
+    pub fn current(&self) -> Option<(u8, u8)> {
+        self.outputs.last().copied()
+    }
+}
+
+impl DisplayPort for MockDisplay {
+    fn output(&mut self, symbol: u8, position: u8) {
+        self.outputs.push((symbol, position));
+    }
+
+    fn fail(&mut self, symbol: u8) {
+        self.failed = Some(symbol);
+    }
+}
+
+/// Records measurements requested by the device.
+#[derive(Default)]
+pub struct MockSampler {
+    /// The measurement that's currently in progress.
+    pub measuring: Option<Measurement>,
+    /// Every measurement started, oldest first.
+    pub started: Vec<Measurement>,
+}
+
+impl Sampler for MockSampler {
+    fn start(&mut self, m: &Measurement) {
+        self.measuring = Some(*m);
+        self.started.push(*m);
+    }
+
+    fn stop(&mut self) {
+        self.measuring = None;
+    }
+}
+
+/// Records tick rate changes requested by the device.
+pub struct MockTimer {
+    /// The currently active tick rate.
+    pub rate: TickRate,
+    /// Every rate change, oldest first.
+    pub changes: Vec<TickRate>,
+}
+
+impl Default for MockTimer {
+    fn default() -> Self {
+        Self {
+            rate: TickRate::Normal,
+            changes: Vec::new(),
+        }
+    }
+}
+
+impl Timer for MockTimer {
+    fn set_rate(&mut self, rate: TickRate) {
+        self.rate = rate;
+        self.changes.push(rate);
+    }
+}
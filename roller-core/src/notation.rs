@@ -0,0 +1,298 @@
+//! Parser of the dice notation, e.g. `4d6kh3`, `2d20kl1`, `1d20+5`, `8d6!`, `10d10>=7` or `2d6+1d8`.
+//!
+//! An expression is made of groups of dice and an optional modifier separated by `+` and `-`. Every group
+//! can be followed by suffixes that apply to the whole roll:
+//!
+//! | Suffix          | Meaning                                                                |
+//! |-----------------|------------------------------------------------------------------------|
+//! | `!`             | the dice explode                                                       |
+//! | `ro<F`, `r<F`   | throws of face F or lower are rerolled once or until a higher face     |
+//! | `khK`, `klK`    | K highest or lowest dice are kept, 1 if K is omitted                   |
+//! | `>=T`, `>=Tf1`  | the dice that meet the target T are counted, 1s cancel successes       |
+//!
+//! The quantity of a group can be omitted for a single die (`d20`), `F` stands for Fudge dice and `%` for
+//! percentile ones. The number of successes takes no modifier. The parser doesn't allocate, the resulting
+//! [`Program`] holds everything the device needs to execute the roll. The device builds the same program
+//! from its own settings for every roll.
+
+use core::fmt;
+
+use crate::device::{Group, Mode, Reroll, Throws, MAX_DICE, MAX_GROUPS, MAX_MODIFIER, MAX_SIDES};
+use crate::random;
+use crate::scales::FUDGE;
+
+/// A parsed roll: the groups of dice and the settings they're rolled with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Program {
+    /// The groups of dice, only the first `group_count` are meaningful.
+    pub groups: [Group; MAX_GROUPS],
+    pub group_count: u8,
+    pub exploding: bool,
+    pub reroll: Reroll,
+    pub mode: Mode,
+    pub modifier: i8,
+}
+
+impl Program {
+    /// Returns a new program without dice that adds them all together.
+    pub const fn new() -> Self {
+        Self {
+            groups: [Group::EMPTY; MAX_GROUPS],
+            group_count: 0,
+            exploding: false,
+            reroll: Reroll::Never,
+            mode: Mode::Sum,
+            modifier: 0,
+        }
+    }
+
+    /// Adds a group of dice. Returns false if the program already has `MAX_GROUPS` groups.
+    pub fn add(&mut self, group: Group) -> bool {
+        if self.group_count as usize == MAX_GROUPS {
+            return false;
+        }
+        self.groups[self.group_count as usize] = group;
+        self.group_count += 1;
+        true
+    }
+
+    /// Returns the groups of dice.
+    pub fn groups(&self) -> &[Group] {
+        &self.groups[..self.group_count as usize]
+    }
+
+    /// Returns the empty throws of the roll and the params of the random generation for every group of dice.
+    /// Fudge dice are thrown as d3, they don't explode and aren't rerolled.
+    pub fn start(&self) -> (Throws, [random::Params; MAX_GROUPS]) {
+        let first = self.groups[0];
+        let fudge = first.sides == FUDGE;
+        let reroll = if fudge { Reroll::Never } else { self.reroll };
+        let mut throws = Throws::new(first.quantity, first.sides, self.exploding && !fudge, reroll);
+        for group in &self.groups()[1..] {
+            throws.add(*group);
+        }
+        // the params of the missing groups are never used
+        let params = core::array::from_fn(|i| {
+            let sides = throws.groups().get(i).map_or(first.sides, |group| group.sides);
+            random::params_for(if sides == FUDGE { 3 } else { sides })
+        });
+        (throws, params)
+    }
+}
+
+impl Default for Program {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Renders the program in the notation it's parsed from, the way the console reports the settings.
+impl fmt::Display for Program {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, group) in self.groups().iter().enumerate() {
+            if i > 0 {
+                f.write_str("+")?;
+            }
+            match group.sides {
+                FUDGE => write!(f, "{}dF", group.quantity)?,
+                sides => write!(f, "{}d{}", group.quantity, sides)?,
+            }
+        }
+        if self.exploding {
+            f.write_str("!")?;
+        }
+        match self.reroll {
+            Reroll::Never => {}
+            Reroll::Once(face) => write!(f, "ro<{}", face)?,
+            Reroll::Always(face) => write!(f, "r<{}", face)?,
+        }
+        match self.mode {
+            Mode::Sum => {}
+            Mode::KeepHighest(k) => write!(f, "kh{}", k)?,
+            Mode::KeepLowest(k) => write!(f, "kl{}", k)?,
+            Mode::Successes { target, botches } => {
+                write!(f, ">={}", target)?;
+                if botches {
+                    f.write_str("f1")?;
+                }
+            }
+        }
+        if self.modifier != 0 {
+            write!(f, "{:+}", self.modifier)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reasons for an expression to be rejected. Positions are byte offsets into the expression.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Error {
+    /// The expression doesn't follow the notation at the specified position.
+    Syntax(usize),
+    /// The number at the specified position is out of range, e.g. a d1, a d1001 or a modifier past 99.
+    OutOfRange(usize),
+    /// The expression has more than `MAX_DICE` dice.
+    TooManyDice,
+    /// The expression has more than `MAX_GROUPS` groups of dice.
+    TooManyGroups,
+    /// Fudge dice are mixed with other dice.
+    MixedFudge,
+    /// The successes of Fudge dice are counted.
+    FudgeSuccesses,
+    /// A modifier is added to the number of successes.
+    ModifiedSuccesses,
+}
+
+impl Error {
+    /// Returns the description of the error.
+    pub fn message(&self) -> &'static str {
+        match self {
+            Error::Syntax(_) => "unexpected character",
+            Error::OutOfRange(_) => "number out of range",
+            Error::TooManyDice => "too many dice",
+            Error::TooManyGroups => "too many groups of dice",
+            Error::MixedFudge => "Fudge dice can't be mixed with other dice",
+            Error::FudgeSuccesses => "successes can't be counted on Fudge dice",
+            Error::ModifiedSuccesses => "successes can't be modified",
+        }
+    }
+}
+
+/// Reads the expression one byte at a time.
+struct Cursor<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl Cursor<'_> {
+    /// Skips the prefix and returns true if the rest of the expression starts with it.
+    fn eat(&mut self, prefix: &str) -> bool {
+        let matches = self.bytes[self.at..].starts_with(prefix.as_bytes());
+        if matches {
+            self.at += prefix.len();
+        }
+        matches
+    }
+
+    /// Reads a decimal number, if the rest of the expression starts with one. Numbers saturate at `u16::MAX`.
+    fn number(&mut self) -> Option<u16> {
+        let start = self.at;
+        let mut n = 0u16;
+        while let Some(d) = self.bytes.get(self.at).filter(|b| b.is_ascii_digit()) {
+            n = n.saturating_mul(10).saturating_add((d - b'0') as u16);
+            self.at += 1;
+        }
+        (self.at > start).then_some(n)
+    }
+
+    /// Reads a number in the specified range.
+    fn number_in(&mut self, min: u16, max: u16) -> Result<u16, Error> {
+        let start = self.at;
+        let n = self.number().ok_or(Error::Syntax(start))?;
+        if n < min || n > max {
+            return Err(Error::OutOfRange(start));
+        }
+        Ok(n)
+    }
+}
+
+/// Parses an expression in the dice notation.
+pub fn parse(expression: &str) -> Result<Program, Error> {
+    let mut c = Cursor { bytes: expression.as_bytes(), at: 0 };
+    let mut program = Program::new();
+    let mut modified = false;
+    let mut negative = false;
+
+    loop {
+        let start = c.at;
+        let quantity = c.number();
+        if c.eat("d") {
+            if negative {
+                return Err(Error::Syntax(start));
+            }
+            if quantity.is_some_and(|q| q == 0 || q > MAX_DICE as u16) {
+                return Err(Error::OutOfRange(start));
+            }
+            let sides = if c.eat("F") {
+                FUDGE
+            } else if c.eat("%") {
+                100
+            } else {
+                c.number_in(2, MAX_SIDES)?
+            };
+            if !program.add(Group { quantity: quantity.unwrap_or(1) as u8, sides }) {
+                return Err(Error::TooManyGroups);
+            }
+            parse_suffixes(&mut c, &mut program)?;
+        } else {
+            // the modifier follows the dice and appears only once
+            let Some(modifier) = quantity.filter(|_| program.group_count > 0 && !modified) else {
+                return Err(Error::Syntax(start));
+            };
+            if modifier > MAX_MODIFIER as u16 {
+                return Err(Error::OutOfRange(start));
+            }
+            program.modifier = if negative { -(modifier as i8) } else { modifier as i8 };
+            modified = true;
+        }
+
+        negative = match c.bytes.get(c.at) {
+            None => break,
+            Some(b'+') => false,
+            Some(b'-') => true,
+            Some(_) => return Err(Error::Syntax(c.at)),
+        };
+        c.at += 1;
+    }
+
+    let groups = program.groups();
+    if groups.iter().map(|g| g.quantity as usize).sum::<usize>() > MAX_DICE {
+        return Err(Error::TooManyDice);
+    }
+    if groups.len() > 1 && groups.iter().any(|g| g.sides == FUDGE) {
+        return Err(Error::MixedFudge);
+    }
+    if groups[0].sides == FUDGE && matches!(program.mode, Mode::Successes { .. }) {
+        return Err(Error::FudgeSuccesses);
+    }
+    if modified && matches!(program.mode, Mode::Successes { .. }) {
+        return Err(Error::ModifiedSuccesses);
+    }
+    Ok(program)
+}
+
+/// Parses the suffixes following a group of dice. Every suffix appears at most once in the expression.
+fn parse_suffixes(c: &mut Cursor, program: &mut Program) -> Result<(), Error> {
+    loop {
+        let start = c.at;
+        if c.eat("!") {
+            if program.exploding {
+                return Err(Error::Syntax(start));
+            }
+            program.exploding = true;
+        } else if c.eat("ro<") || c.eat("r<") {
+            let once = c.bytes[start + 1] == b'o';
+            let face = c.number_in(1, u8::MAX as u16)? as u8;
+            if program.reroll != Reroll::Never {
+                return Err(Error::Syntax(start));
+            }
+            program.reroll = if once { Reroll::Once(face) } else { Reroll::Always(face) };
+        } else if c.eat("kh") || c.eat("kl") {
+            let highest = c.bytes[start + 1] == b'h';
+            let k = if c.bytes.get(c.at).is_some_and(u8::is_ascii_digit) { c.number_in(1, MAX_DICE as u16)? as u8 } else { 1 };
+            if program.mode != Mode::Sum {
+                return Err(Error::Syntax(start));
+            }
+            program.mode = if highest { Mode::KeepHighest(k) } else { Mode::KeepLowest(k) };
+        } else if c.eat(">=") {
+            let target = c.number_in(1, u8::MAX as u16)? as u8;
+            let botches = c.eat("f1");
+            if program.mode != Mode::Sum {
+                return Err(Error::Syntax(start));
+            }
+            program.mode = Mode::Successes { target, botches };
+        } else {
+            return Ok(());
+        }
+    }
+}
@@ -0,0 +1,180 @@
+//! Framed protocol of the debug channel.
+//!
+//! Every message is sent as a frame:
+//!
+//! | Byte      | Content                                                  |
+//! |-----------|----------------------------------------------------------|
+//! | 0         | `SYNC`                                                   |
+//! | 1         | message type                                             |
+//! | 2         | payload length n                                         |
+//! | 3..3+n    | payload, 16-bit values are big-endian                    |
+//! | 3+n       | CRC-8 (polynomial 0x07) of the type, length and payload  |
+//!
+//! The receiver can start listening at any moment: it resynchronises by looking for a `SYNC` byte
+//! that starts a frame with a known type, a matching length and a valid checksum.
+
+use crate::device::MAX_DICE;
+
+/// Marks the start of a frame.
+pub const SYNC: u8 = 0xA5;
+
+/// The largest payload of a frame.
+pub const MAX_PAYLOAD: usize = 3 + 2 * MAX_DICE;
+
+/// The largest size of a frame.
+pub const MAX_FRAME: usize = 4 + MAX_PAYLOAD;
+
+const TYPE_ACC: u8 = 1;
+const TYPE_POTS: u8 = 2;
+const TYPE_STATE: u8 = 3;
+const TYPE_ENTROPY: u8 = 4;
+const TYPE_RESULT: u8 = 5;
+
+/// Identifies device states in `Message::State`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StateId {
+    Displaying = 0,
+    Rolling = 1,
+    Sleeping = 2,
+    Setup = 3,
+}
+
+impl StateId {
+    /// Returns the state identified by the code, if any.
+    pub fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(StateId::Displaying),
+            1 => Some(StateId::Rolling),
+            2 => Some(StateId::Sleeping),
+            3 => Some(StateId::Setup),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the state.
+    pub fn name(&self) -> &'static str {
+        match self {
+            StateId::Displaying => "Displaying",
+            StateId::Rolling => "Rolling",
+            StateId::Sleeping => "Sleeping",
+            StateId::Setup => "Setup",
+        }
+    }
+}
+
+/// Messages sent over the debug channel.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Message {
+    /// Raw AccX, AccY and AccZ measurements taken during a single timer tick.
+    Acc([u16; 3]),
+    /// Raw quantity and quality pot measurements taken during a single timer tick.
+    Pots([u16; 2]),
+    /// The device has entered a new state.
+    State(StateId),
+    /// The entropy a die throw has just been generated from, the highest 16 bits of the random number.
+    Entropy(u16),
+    /// The result of a roll: the sum of the individual dice, the modifier added to it and the dice.
+    Result { sum: u16, modifier: i8, dice: [u16; MAX_DICE], count: u8 },
+}
+
+/// Outcome of an attempt to decode a frame.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Decoded {
+    /// A valid frame of the specified size has been decoded.
+    Frame(Message, usize),
+    /// The bytes look like the beginning of a frame, but more bytes are needed.
+    Incomplete,
+    /// The bytes don't start with a valid frame.
+    Invalid,
+}
+
+/// Calculates CRC-8 with the polynomial 0x07.
+fn crc8(bytes: &[u8]) -> u8 {
+    let mut crc = 0u8;
+    for b in bytes {
+        crc ^= b;
+        for _ in 0..8 {
+            crc = if crc & 0x80 != 0 { (crc << 1) ^ 0x07 } else { crc << 1 };
+        }
+    }
+    crc
+}
+
+impl Message {
+    /// Encodes the message as a frame into the provided buffer. Returns the size of the frame.
+    pub fn encode(&self, buf: &mut [u8; MAX_FRAME]) -> usize {
+        let payload = &mut buf[3..];
+        let mut put_u16 = |at: usize, n: u16| payload[at..at + 2].copy_from_slice(&n.to_be_bytes());
+
+        let (kind, len) = match self {
+            Message::Acc(values) => {
+                values.iter().enumerate().for_each(|(i, n)| put_u16(i * 2, *n));
+                (TYPE_ACC, 6)
+            }
+            Message::Pots(values) => {
+                values.iter().enumerate().for_each(|(i, n)| put_u16(i * 2, *n));
+                (TYPE_POTS, 4)
+            }
+            Message::State(id) => {
+                payload[0] = *id as u8;
+                (TYPE_STATE, 1)
+            }
+            Message::Entropy(entropy) => {
+                put_u16(0, *entropy);
+                (TYPE_ENTROPY, 2)
+            }
+            Message::Result { sum, modifier, dice, count } => {
+                let count = *count as usize;
+                put_u16(0, *sum);
+                dice[..count].iter().enumerate().for_each(|(i, d)| put_u16(3 + i * 2, *d));
+                payload[2] = *modifier as u8;
+                (TYPE_RESULT, 3 + count * 2)
+            }
+        };
+
+        buf[0] = SYNC;
+        buf[1] = kind;
+        buf[2] = len as u8;
+        buf[3 + len] = crc8(&buf[1..3 + len]);
+        4 + len
+    }
+
+    /// Tries to decode a frame from the beginning of the provided bytes.
+    pub fn decode(bytes: &[u8]) -> Decoded {
+        if bytes.len() < 3 {
+            return if matches!(bytes.first(), None | Some(&SYNC)) { Decoded::Incomplete } else { Decoded::Invalid };
+        }
+
+        let (kind, len) = (bytes[1], bytes[2] as usize);
+        if bytes[0] != SYNC || len > MAX_PAYLOAD {
+            return Decoded::Invalid;
+        }
+        if bytes.len() < 4 + len {
+            return Decoded::Incomplete;
+        }
+        if crc8(&bytes[1..3 + len]) != bytes[3 + len] {
+            return Decoded::Invalid;
+        }
+
+        let payload = &bytes[3..3 + len];
+        let u16_at = |i: usize| u16::from_be_bytes([payload[i], payload[i + 1]]);
+        let message = match (kind, len) {
+            (TYPE_ACC, 6) => Message::Acc([u16_at(0), u16_at(2), u16_at(4)]),
+            (TYPE_POTS, 4) => Message::Pots([u16_at(0), u16_at(2)]),
+            (TYPE_STATE, 1) => match StateId::from_code(payload[0]) {
+                Some(id) => Message::State(id),
+                None => return Decoded::Invalid,
+            },
+            (TYPE_ENTROPY, 2) => Message::Entropy(u16_at(0)),
+            (TYPE_RESULT, 3..) if len % 2 == 1 => {
+                let count = (len - 3) / 2;
+                let mut dice = [0; MAX_DICE];
+                dice[..count].iter_mut().enumerate().for_each(|(i, d)| *d = u16_at(3 + i * 2));
+                Message::Result { sum: u16_at(0), modifier: payload[2] as i8, dice, count: count as u8 }
+            }
+            _ => return Decoded::Invalid,
+        };
+
+        Decoded::Frame(message, 4 + len)
+    }
+}
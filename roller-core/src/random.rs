@@ -0,0 +1,28 @@
+//! Transforming 16-bit entropy into unbiased numbers below a bound, with Lemire's method: the entropy is
+//! multiplied by the bound and the high half of the product is the result. The few products whose low half
+//! falls below `2^16 mod bound` would make some results more likely than the others and are rejected, at most
+//! `bound / 2^16` of the entropy is thrown away that way.
+
+/// Precalculated params for random result generation
+pub struct Params {
+    bound: u16,
+    /// Products whose low half is below this are rejected, 0 for powers of two.
+    threshold: u16,
+}
+
+/// Precalculate params for random result generation. The bound has to be at least 1.
+pub const fn params_for(bound: u16) -> Params {
+    Params {
+        bound,
+        threshold: ((1u32 << 16) % bound as u32) as u16,
+    }
+}
+
+/// Try to generate a random number below the bound from the given precalculated params and entropy.
+pub fn generate(params: &Params, entropy: u16) -> Option<u16> {
+    let product = entropy as u32 * params.bound as u32;
+    if (product as u16) < params.threshold {
+        return None;
+    }
+    Some((product >> 16) as u16)
+}
@@ -0,0 +1,128 @@
+/// Represents a range on a number scale mapped to a value.
+pub struct Zone<V = u8> {
+    pub value: V,
+    pub start: u16,
+    pub end: u16,
+}
+
+/// Provides a shortcut to initialize a Zone.
+const fn zone<V>(value: V, start: u16, end: u16) -> Zone<V> {
+    Zone {
+        value,
+        start,
+        end,
+    }
+}
+
+/// Values of the quantity zones past 1 that select rolling two dice and keeping the higher (advantage)
+/// or the lower (disadvantage) one.
+pub const ADVANTAGE: u8 = 0xfe;
+pub const DISADVANTAGE: u8 = 0xff;
+/// Value of the narrow quantity zone past disadvantage that turns the quality knob into the settings menu.
+pub const SETUP: u8 = 0xfd;
+
+/// Maps values of 1-20 followed by the advantage and disadvantage modes to ~equal zones on the 0-1023 scale.
+/// The setup zone at the very end takes a part of the last two.
+pub const QUANTITY: [Zone; 23] = [
+    zone(20, 0, 46),
+    zone(19, 47, 92),
+    zone(18, 93, 139),
+    zone(17, 140, 185),
+    zone(16, 186, 232),
+    zone(15, 233, 278),
+    zone(14, 279, 325),
+    zone(13, 326, 371),
+    zone(12, 372, 418),
+    zone(11, 419, 464),
+    zone(10, 465, 511),
+    zone(9, 512, 558),
+    zone(8, 559, 604),
+    zone(7, 605, 651),
+    zone(6, 652, 697),
+    zone(5, 698, 744),
+    zone(4, 745, 790),
+    zone(3, 791, 837),
+    zone(2, 838, 883),
+    zone(1, 884, 930),
+    zone(ADVANTAGE, 931, 961),
+    zone(DISADVANTAGE, 962, 992),
+    zone(SETUP, 993, 1023),
+];
+
+/// Value of the last quality zone that selects the custom side count configured on the device.
+pub const CUSTOM: u16 = 0;
+/// Value of the quality zone past d2 that selects Fudge dice: 3 sides standing for -1, 0 and +1.
+pub const FUDGE: u16 = 1;
+
+/// Maps values representing side counts of typical board game dice followed by Fudge dice and
+/// the custom side count to ~equal zones on the 0-1023 scale.
+pub const QUALITY: [Zone<u16>; 11] = [
+    zone(100, 0, 92),
+    zone(20, 93, 185),
+    zone(12, 186, 278),
+    zone(10, 279, 371),
+    zone(8, 372, 464),
+    zone(6, 465, 557),
+    zone(4, 558, 650),
+    zone(3, 651, 743),
+    zone(2, 744, 836),
+    zone(FUDGE, 837, 929),
+    zone(CUSTOM, 930, 1023),
+];
+
+/// Returns the matching zone given the position on the corresponding scale.
+pub fn detect_zone<V>(position: u16, zones: &[Zone<V>]) -> &Zone<V> {
+    for zone in zones {
+        if zone.start <= position && zone.end >= position {
+            return zone;
+        }
+    }
+
+    panic!()
+}
+
+/// Detects whether a transition from the current zone into the new one should be made given
+/// the new position on the scale and taking simple debouncing rules into account. Returns the new
+/// zone or None if no transition should be made.
+///
+/// Debouncing is useful when the new scale position comes from a noisy source like an ADC.
+/// If there was no debouncing and the position was right between two adjacent zones,
+/// simple range matching would be constantly switching between the two zones.
+///
+/// This implementation registers a transition only if the new position crosses the specified
+/// dead area following or preceding the current zone depending on the direction of the transition.
+pub fn detect_zone_change<V>(new_position: u16, dead_area: u16, current_zone: &'static Zone<V>,
+                             all_zones: &'static [Zone<V>]) -> Option<&'static Zone<V>> {
+    if current_zone.start > dead_area && new_position < (current_zone.start - dead_area) {
+        for s in all_zones {
+            if new_position < s.end - dead_area {
+                return Some(s);
+            }
+        }
+    } else if new_position > current_zone.end + dead_area {
+        for i in (0..all_zones.len()).rev() {
+            if new_position > all_zones[i].start + dead_area {
+                return Some(&all_zones[i]);
+            }
+        }
+    }
+
+    None
+}
+
+/// Returns the index of the step the position falls into when the 0-1023 scale is divided into `count`
+/// ~equal steps. Like zone changes, a transition from the current step is only registered once the position
+/// crosses the dead area around it.
+pub fn detect_step(position: u16, count: u16, current: Option<u16>, dead_area: u16) -> u16 {
+    let step = (position as u32 * count as u32 / 1024) as u16;
+    let Some(current) = current else {
+        return step;
+    };
+    let start = (current as u32 * 1024 / count as u32) as u16;
+    let end = ((current as u32 + 1) * 1024 / count as u32) as u16 - 1;
+    if position + dead_area < start || position > end + dead_area {
+        step
+    } else {
+        current
+    }
+}
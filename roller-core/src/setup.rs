@@ -0,0 +1,281 @@
+//! The settings menu worked with the knobs. Once the quantity knob is turned past disadvantage into the setup
+//! zone, the quality knob picks a page of the menu: its label with a dot followed by the current value of the
+//! setting. Shaking the device opens the page, the dot goes out and the quality knob picks the value. Shaking
+//! the device again applies the value and returns to the pages.
+//!
+//! Once the page is closed, the menu stays on it until the knob is turned rather than jumping to the page under
+//! the value.
+
+use crate::device::{Mode, Reroll};
+use crate::display::{self, segment, symbol, Buffer};
+use crate::scales;
+
+/// How far past a step the quality knob has to be turned to leave it, in ADC steps.
+const DEAD_AREA: u16 = 4;
+/// How far the quality knob has to be turned to leave the page it has been closed on, in ADC steps.
+const HOLD_AREA: u16 = 16;
+
+/// Pages of the menu, in the order of the quality knob.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Page {
+    /// The modifier added to the sum.
+    Modifier,
+    /// Keeping a few of the lowest or the highest dice, or all of them.
+    Keep,
+    /// Whether the dropped dice are shown after the total.
+    Dropped,
+    /// Whether the dice that show their maximum face are thrown again.
+    Exploding,
+    /// Counting the dice that meet a target, with or without botches.
+    Count,
+    /// Whether every die is shown after the total.
+    Dice,
+    /// The side count selected by the custom quality zone.
+    Sides,
+    /// Whether critical rolls are animated before the total.
+    Critical,
+    /// Rerolling the low throws once or until a higher face comes up.
+    Reroll,
+    /// Adding the selected dice to the pool rolled along with them, or emptying it.
+    Pool,
+}
+
+impl Page {
+    pub const ALL: [Page; 10] = [
+        Page::Modifier, Page::Keep, Page::Dropped, Page::Exploding, Page::Count, Page::Dice, Page::Sides, Page::Critical,
+        Page::Reroll, Page::Pool,
+    ];
+
+    /// The largest absolute value of the modifier picked on its page. Larger ones can be set over the console.
+    pub const MAX_MODIFIER: i8 = 20;
+    /// The largest number of the lowest or the highest dice kept.
+    pub const MAX_KEEP: u8 = 19;
+    /// The highest target of the successes.
+    pub const MAX_TARGET: u8 = 20;
+    /// The largest custom side count picked on its page. The values share the ~1000 ADC steps of the quality
+    /// knob, up to d100 every value takes about 10 of them, well past `DEAD_AREA`. Up to `device::MAX_SIDES`,
+    /// a value would take a single step and the noise of the knob would pick the next one. Larger side counts
+    /// are set over the console.
+    pub const MAX_SIDES: u16 = 100;
+    /// The highest rerolled face picked on its page. Higher ones can be set over the console.
+    pub const MAX_REROLL: u8 = 9;
+
+    /// Returns how many values the page has.
+    pub fn values(&self) -> u16 {
+        match self {
+            Page::Modifier => Self::MAX_MODIFIER as u16 * 2 + 1,
+            Page::Keep => Self::MAX_KEEP as u16 * 2 + 1,
+            Page::Dropped | Page::Exploding | Page::Dice | Page::Critical | Page::Pool => 2,
+            // the targets from 2 up, then the same with botches
+            Page::Count => 1 + (Self::MAX_TARGET as u16 - 1) * 2,
+            Page::Sides => Self::MAX_SIDES - 1,
+            // the faces from 1 up rerolled once, then the same until a higher face comes up
+            Page::Reroll => 1 + Self::MAX_REROLL as u16 * 2,
+        }
+    }
+
+    /// Returns the value with the specified index.
+    pub fn setting(&self, index: u16) -> Setting {
+        match self {
+            Page::Modifier => Setting::Modifier(index as i8 - Self::MAX_MODIFIER),
+            // the lowest dice first, the most of them at the start
+            Page::Keep => Setting::Keep(match index as u8 {
+                i if i < Self::MAX_KEEP => Mode::KeepLowest(Self::MAX_KEEP - i),
+                i if i == Self::MAX_KEEP => Mode::Sum,
+                i => Mode::KeepHighest(i - Self::MAX_KEEP),
+            }),
+            Page::Dropped => Setting::Dropped(index == 1),
+            Page::Exploding => Setting::Exploding(index == 1),
+            Page::Count => Setting::Count(match index as u8 {
+                0 => None,
+                i if i < Self::MAX_TARGET => Some((i + 1, false)),
+                i => Some((i - Self::MAX_TARGET + 2, true)),
+            }),
+            Page::Dice => Setting::Dice(index == 1),
+            Page::Sides => Setting::Sides(index + 2),
+            Page::Critical => Setting::Critical(index == 1),
+            Page::Reroll => Setting::Reroll(match index as u8 {
+                0 => Reroll::Never,
+                i if i <= Self::MAX_REROLL => Reroll::Once(i),
+                i => Reroll::Always(i - Self::MAX_REROLL),
+            }),
+            Page::Pool => Setting::Pool(index == 1),
+        }
+    }
+
+    /// Returns the symbol the page is labeled with: its number, or "P" for the pool.
+    fn label(&self) -> u8 {
+        if *self == Page::Pool {
+            return symbol::PAPA;
+        }
+        let index = Self::ALL.iter().position(|page| page == self).unwrap_or(0);
+        symbol::MAP[index + 1]
+    }
+}
+
+/// A value of the setting of a page.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Setting {
+    Modifier(i8),
+    /// Any mode that doesn't keep a few of the dice is shown as keeping all of them.
+    Keep(Mode),
+    Dropped(bool),
+    Exploding(bool),
+    /// The target and whether 1s are botches, None for not counting the successes.
+    Count(Option<(u8, bool)>),
+    Dice(bool),
+    Sides(u16),
+    Critical(bool),
+    Reroll(Reroll),
+    /// Adds the selected dice to the pool if set, empties it otherwise.
+    Pool(bool),
+    /// How many groups of dice the pool has, shown while its page is closed.
+    Pooled(u8),
+}
+
+impl Setting {
+    /// Renders the value into the last three displays, aligned to the right.
+    fn render(&self) -> [u8; 3] {
+        let mut buf = [0u8; 3];
+        let len = match *self {
+            // "+2", "-2" or "0"
+            Setting::Modifier(modifier) => display::encode_i16_into(&mut buf, modifier as i16, true),
+            // "H3" or "L3" like in the settings view, a minus for keeping all the dice
+            Setting::Keep(Mode::KeepHighest(k)) => { buf[0] = symbol::HOTEL; 1 + display::encode_u8_into(&mut buf[1..], k) }
+            Setting::Keep(Mode::KeepLowest(k)) => { buf[0] = symbol::LIMA; 1 + display::encode_u8_into(&mut buf[1..], k) }
+            Setting::Keep(_) => { buf[0] = symbol::MINUS; 1 }
+            Setting::Dropped(on) | Setting::Exploding(on) | Setting::Dice(on) | Setting::Critical(on) => {
+                Self::render_switch(&mut buf, on)
+            }
+            // the bars followed by the target like in the settings view, with a dot if 1s are botches
+            Setting::Count(Some((target, botches))) => {
+                buf[0] = symbol::BARS | if botches { segment::POINT } else { 0 };
+                let target_len = display::encode_u8_into(&mut buf[1..], target);
+                buf[1..].rotate_right(2 - target_len);
+                3
+            }
+            Setting::Count(None) => { buf[0] = symbol::MINUS; 1 }
+            // "d30", three-digit side counts leave no room for the "d"
+            Setting::Sides(sides) if sides >= 100 => display::encode_u16_into(&mut buf, sides),
+            Setting::Sides(sides) => { buf[0] = symbol::DELTA; 1 + display::encode_u16_into(&mut buf[1..], sides) }
+            // "r2" or "rr2" like in the settings view
+            Setting::Reroll(Reroll::Once(face) | Reroll::Always(face)) => {
+                let face_len = display::encode_u8_into(&mut buf, face);
+                buf.rotate_right(3 - face_len);
+                if face_len < 3 {
+                    buf[2 - face_len] = symbol::ROMEO;
+                }
+                if matches!(*self, Setting::Reroll(Reroll::Always(_))) && face_len < 2 {
+                    buf[1 - face_len] = symbol::ROMEO;
+                }
+                3
+            }
+            Setting::Reroll(Reroll::Never) => { buf[0] = symbol::MINUS; 1 }
+            Setting::Pool(true) => { buf.copy_from_slice(&[symbol::ALPHA, symbol::DELTA, symbol::DELTA]); 3 }
+            Setting::Pool(false) => { buf.copy_from_slice(&[symbol::CHARLIE, symbol::LIMA, symbol::ROMEO]); 3 }
+            Setting::Pooled(groups) => display::encode_u8_into(&mut buf, groups),
+        };
+        buf.rotate_right(3 - len);
+        buf
+    }
+
+    /// Renders "on" or "oFF". Returns the number of symbols rendered.
+    fn render_switch(buf: &mut [u8], on: bool) -> usize {
+        if on {
+            buf[..2].copy_from_slice(&[symbol::OSCAR, symbol::NOVEMBER]);
+            2
+        } else {
+            buf.copy_from_slice(&[symbol::OSCAR, symbol::FOXTROT, symbol::FOXTROT]);
+            3
+        }
+    }
+}
+
+/// The state of the menu.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Menu {
+    /// The index of the page in `Page::ALL`.
+    page: u16,
+    /// The index of the value picked on the open page, None while a page is being picked.
+    value: Option<u16>,
+    /// The step of the quality knob the page or the value has been picked with.
+    step: u16,
+    /// The position of the quality knob the page has been closed at, until the knob leaves it.
+    held: Option<u16>,
+}
+
+impl Menu {
+    /// Returns the menu on the page the quality knob is on.
+    pub fn new(position: u16) -> Self {
+        let page = scales::detect_step(position, Page::ALL.len() as u16, None, 0);
+        Self { page, value: None, step: page, held: None }
+    }
+
+    /// Returns the current page.
+    pub fn page(&self) -> Page {
+        Page::ALL[self.page as usize]
+    }
+
+    /// Returns whether the page is open.
+    pub fn is_open(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// Follows the quality knob to the next page or value. Returns whether either has changed.
+    pub fn turn(&mut self, position: u16) -> bool {
+        if let Some(held) = self.held {
+            if position.abs_diff(held) <= HOLD_AREA {
+                return false;
+            }
+            self.held = None;
+            self.step = scales::detect_step(position, Page::ALL.len() as u16, None, 0);
+            let page = self.page;
+            self.page = self.step;
+            return self.page != page;
+        }
+
+        let count = if self.is_open() { self.page().values() } else { Page::ALL.len() as u16 };
+        let step = scales::detect_step(position, count, Some(self.step), DEAD_AREA);
+        if step == self.step {
+            return false;
+        }
+        self.step = step;
+        match &mut self.value {
+            Some(value) => *value = step,
+            None => self.page = step,
+        }
+        true
+    }
+
+    /// Opens the current page on the value the quality knob is on.
+    pub fn open(&mut self, position: u16) {
+        self.step = scales::detect_step(position, self.page().values(), None, 0);
+        self.value = Some(self.step);
+        self.held = None;
+    }
+
+    /// Closes the open page. Returns the value picked on it.
+    pub fn close(&mut self, position: u16) -> Option<Setting> {
+        let value = self.value.take()?;
+        self.held = Some(position);
+        Some(self.page().setting(value))
+    }
+
+    /// Renders the label of the current page followed by the picked value, or by the current value of the
+    /// setting while the page is closed. Four-digit side counts, only set over the console, take the place
+    /// of the label.
+    pub fn render(&self, current: Setting) -> Buffer {
+        let (setting, dot) = match self.value {
+            Some(value) => (self.page().setting(value), 0),
+            None => (current, segment::POINT),
+        };
+        if let Setting::Sides(sides @ 1000..) = setting {
+            let mut buf = [0u8; 4];
+            display::encode_u16_into(&mut buf, sides);
+            buf[0] |= dot;
+            return buf;
+        }
+        let [a, b, c] = setting.render();
+        [self.page().label() | dot, a, b, c]
+    }
+}
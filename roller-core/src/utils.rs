@@ -25,6 +25,11 @@ impl<T: Copy + PartialOrd + Sub<Output=T>, const SIZE: usize> Agg<T, SIZE> {
         self.next_put_at = (self.next_put_at + 1) % self.data.len();
     }
 
+    /// Returns the element stored at the specified index of the ring buffer, if any.
+    pub fn get(&self, index: usize) -> Option<T> {
+        self.data.get(index).copied().flatten()
+    }
+
     /// Returns the sum of the first n elements represented as type S.
     /// Returns None if there are fewer than n elements in the buffer.
     pub fn sum_of_first<S: 'static + Copy + Default + Add<Output=S>>(&self, n: usize) -> Option<S>
@@ -68,11 +73,7 @@ impl<T: Copy + PartialOrd + Sub<Output=T>, const SIZE: usize> Agg<T, SIZE> {
               T: AsPrimitive<S>,
               S: AsPrimitive<T>,
     {
-        let sum = if let Some(sum) = self.sum_of_first::<S>(n) {
-            sum
-        } else {
-            return None;
-        };
+        let sum = self.sum_of_first::<S>(n)?;
 
         let avg = sum / AsPrimitive::<S>::as_(n);
 
@@ -131,22 +132,25 @@ impl<T: Copy + PartialOrd + Sub<Output=T>, const SIZE: usize> Agg<T, SIZE> {
     }
 }
 
-#[cfg(feature = "debug_spi")]
-const RING_SIZE: usize = 16;
+impl<T: Copy + PartialOrd + Sub<Output=T>, const SIZE: usize> Default for Agg<T, SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-#[cfg(feature = "debug_spi")]
-pub struct Ring {
-    buffer: [u8; RING_SIZE],
+#[cfg(any(feature = "debug_spi", feature = "console"))]
+pub struct Ring<const SIZE: usize> {
+    buffer: [u8; SIZE],
     next_write: usize,
     next_read: usize,
     full: bool,
 }
 
-#[cfg(feature = "debug_spi")]
-impl Ring {
+#[cfg(any(feature = "debug_spi", feature = "console"))]
+impl<const SIZE: usize> Ring<SIZE> {
     pub const fn new() -> Self {
         Self {
-            buffer: [0; RING_SIZE],
+            buffer: [0; SIZE],
             next_write: 0,
             next_read: 0,
             full: false,
@@ -154,12 +158,13 @@ impl Ring {
     }
 
     pub fn read(&mut self) -> Option<u8> {
-        if self.next_read == self.next_write {
+        if self.next_read == self.next_write && !self.full {
             return None;
         }
 
         let result = self.buffer[self.next_read];
-        self.next_read = (self.next_read + 1) % RING_SIZE;
+        self.next_read = (self.next_read + 1) % SIZE;
+        self.full = false;
 
         Some(result)
     }
@@ -170,7 +175,27 @@ impl Ring {
         }
 
         self.buffer[self.next_write] = data;
-        self.next_write = (self.next_write + 1) % RING_SIZE;
+        self.next_write = (self.next_write + 1) % SIZE;
         self.full = self.next_write == self.next_read;
     }
+
+    pub fn is_full(&self) -> bool {
+        self.full
+    }
+
+    /// Returns how many more bytes can be written.
+    pub fn free(&self) -> usize {
+        if self.full {
+            0
+        } else {
+            (self.next_read + SIZE - self.next_write - 1) % SIZE + 1
+        }
+    }
+}
+
+#[cfg(any(feature = "debug_spi", feature = "console"))]
+impl<const SIZE: usize> Default for Ring<SIZE> {
+    fn default() -> Self {
+        Self::new()
+    }
 }
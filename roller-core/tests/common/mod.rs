@@ -0,0 +1,44 @@
+//! Helpers shared by the integration tests: running the device logic against mocked hardware.
+#![allow(dead_code)]
+
+use roller_core::device::Device;
+use roller_core::hardware::Measurement;
+use roller_core::mock::{MockDisplay, MockSampler, MockTimer};
+
+/// The device logic running against mocked hardware.
+pub type Bench = Device<MockDisplay, MockSampler, MockTimer>;
+
+/// Returns a new device running against mocked hardware.
+pub fn bench() -> Bench {
+    Device::new(MockDisplay::default(), MockSampler::default(), MockTimer::default())
+}
+
+/// Simulates a single timer tick, answering every measurement the device starts during the tick
+/// with the value returned by `measure`.
+pub fn tick(device: &mut Bench, mut measure: impl FnMut(Measurement) -> u16) {
+    device.timer_interrupt();
+    while let Some(m) = device.sampler.measuring {
+        device.adc_interrupt(measure(m));
+
+        // nothing is connected to the other end of the debug channel.
+        #[cfg(feature = "debug_spi")]
+        while device.debug_buf.read().is_some() {}
+    }
+}
+
+/// Simulates a single timer tick with the knobs in the specified positions and the accelerometer axes reading
+/// around the specified values: the readings of a healthy accelerometer are never perfectly still.
+pub fn tick_near(device: &mut Bench, (quantity, quality): (u16, u16), [x, y, z]: [u16; 3]) {
+    let mut started = device.sampler.started.len();
+    tick(device, |m| {
+        started += 1;
+        let noise = (started % 3) as u16;
+        match m {
+            Measurement::PotQuantity => quantity,
+            Measurement::PotQuality => quality,
+            Measurement::AccX => x + noise,
+            Measurement::AccY => y + noise,
+            Measurement::AccZ => z + noise,
+        }
+    });
+}
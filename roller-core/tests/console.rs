@@ -0,0 +1,468 @@
+mod common;
+
+use common::{bench, tick_near, Bench};
+use roller_core::console::{parse, Command, Console, Error, Threshold, COMMANDS};
+use roller_core::device::{Group, Mode, Reroll, Roll, State};
+use roller_core::display::segment::{A, G, POINT};
+use roller_core::display::symbol::*;
+use roller_core::scales::{ADVANTAGE, DISADVANTAGE, FUDGE};
+
+/// Simulates a single timer tick with the device resting on the table and the knobs set to 3d6.
+fn tick(device: &mut Bench) {
+    tick_near(device, (814, 511), [511, 511, 767]);
+}
+
+fn ready_bench() -> Bench {
+    let mut device = bench();
+    for _ in 0..40 {
+        tick(&mut device);
+    }
+    device
+}
+
+/// Forces a roll with the active settings and waits for its result.
+fn roll(device: &mut Bench) -> Roll {
+    send(device, "roll\n");
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick(device);
+    }
+    *device.last_roll().unwrap()
+}
+
+/// Feeds the bytes to the console one at a time and collects everything it sends back.
+fn send(device: &mut Bench, input: &str) -> String {
+    let mut output = Vec::new();
+    for b in input.bytes() {
+        device.console_receive(b);
+        while let Some(b) = device.console.read() {
+            output.push(b);
+        }
+    }
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn commands_are_parsed() {
+    assert_eq!(parse(b"state"), Ok(Some(Command::State)));
+    assert_eq!(parse(b"  roll  "), Ok(Some(Command::Roll)));
+    assert_eq!(parse(b""), Ok(None));
+    assert_eq!(parse(b" \t"), Ok(None));
+    assert_eq!(parse(b"set 12d20"), Ok(Some(Command::Set(Some((12, 20))))));
+    assert_eq!(parse(b"set pots"), Ok(Some(Command::Set(None))));
+    assert_eq!(parse(b"set Hd20"), Ok(Some(Command::Set(Some((ADVANTAGE, 20))))));
+    assert_eq!(parse(b"set Ld20"), Ok(Some(Command::Set(Some((DISADVANTAGE, 20))))));
+    assert_eq!(parse(b"set 4dF"), Ok(Some(Command::Set(Some((4, FUDGE))))));
+    assert_eq!(parse(b"pool 2d10"), Ok(Some(Command::Pool(Some(Group { quantity: 2, sides: 10 })))));
+    assert_eq!(parse(b"pool clear"), Ok(Some(Command::Pool(None))));
+    assert_eq!(parse(b"keep h3"), Ok(Some(Command::Keep(Mode::KeepHighest(3)))));
+    assert_eq!(parse(b"keep l1"), Ok(Some(Command::Keep(Mode::KeepLowest(1)))));
+    assert_eq!(parse(b"keep all"), Ok(Some(Command::Keep(Mode::Sum))));
+    assert_eq!(parse(b"count 7 botch"), Ok(Some(Command::Count(Mode::Successes { target: 7, botches: true }))));
+    assert_eq!(parse(b"reroll 2 always"), Ok(Some(Command::Reroll(Reroll::Always(2)))));
+    assert_eq!(parse(b"reroll off"), Ok(Some(Command::Reroll(Reroll::Never))));
+    assert_eq!(parse(b"dropped on"), Ok(Some(Command::Dropped(true))));
+    assert_eq!(parse(b"dice off"), Ok(Some(Command::Dice(false))));
+    assert_eq!(parse(b"threshold"), Ok(Some(Command::Threshold(None))));
+    assert_eq!(parse(b"threshold balance 150"), Ok(Some(Command::Threshold(Some((Threshold::Balance, 150))))));
+
+    assert_eq!(parse(b"jump"), Err(Error::UnknownCommand));
+    assert_eq!(parse(b"state now"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"set"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"set 3x6"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"set 300d6"), Err(Error::InvalidArgument));
+    // advantage and disadvantage have their own keywords
+    assert_eq!(parse(b"set 254d6"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"pool 255d6"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"set 21d6"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"keep x3"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"keep"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"count 7 fail"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"count 0"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"dropped maybe"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"threshold gravity 1"), Err(Error::InvalidArgument));
+    assert_eq!(parse(b"threshold force 1 2"), Err(Error::InvalidArgument));
+    assert_eq!(parse(&[0xff]), Err(Error::UnknownCommand));
+    // every command is known, with or without its arguments
+    for name in COMMANDS {
+        assert_ne!(parse(format!("{} ?", name).as_bytes()), Err(Error::UnknownCommand), "{}", name);
+    }
+}
+
+#[test]
+fn lines_are_collected_and_echoed() {
+    let mut console = Console::new();
+    let mut received = Vec::new();
+    for b in b"stx\x7fate\r\n" {
+        received.extend(console.receive(*b));
+    }
+    assert_eq!(received, [Ok(Command::State)]);
+
+    let echo: Vec<u8> = std::iter::from_fn(|| console.read()).collect();
+    assert_eq!(echo, b"stx\x08 \x08ate\r\n\r\n");
+
+    for b in [b'x'; 40] {
+        assert_eq!(console.receive(b), None);
+    }
+    assert_eq!(console.receive(b'\n'), Some(Err(Error::LineTooLong)));
+    assert_eq!(console.receive(b'\n'), None);
+}
+
+#[test]
+fn device_reports_its_state() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "state\r"), "state\r\nDisplaying\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6\r\n");
+    assert_eq!(send(&mut device, "last\n"), "last\r\nnone\r\n");
+    assert_eq!(send(&mut device, "adc\n"), "adc\r\nquantity 814 quality 511 x 511 y 512 z 769\r\n");
+    assert_eq!(send(&mut device, "nope\n"), "nope\r\nerror: unknown command\r\n");
+    assert_eq!(send(&mut device, "help\n"), format!("help\r\n{}\r\n", COMMANDS.join(" ")));
+}
+
+#[test]
+fn settings_are_not_ready_at_startup() {
+    let mut device = bench();
+    assert_eq!(send(&mut device, "roll\n"), "roll\r\nerror: settings are not ready\r\n");
+}
+
+#[test]
+fn roll_can_be_forced() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "roll\n"), "roll\r\nok\r\n");
+    assert!(matches!(device.state(), State::Rolling { .. }));
+
+    for _ in 0..200 {
+        tick(&mut device);
+    }
+    assert!(matches!(device.state(), State::Displaying { .. }));
+
+    let roll = *device.last_roll().unwrap();
+    assert_eq!(roll.dice().len(), 3);
+    assert_eq!(roll.sum, roll.dice().iter().sum::<u16>());
+
+    let dice: Vec<String> = roll.dice().iter().map(u16::to_string).collect();
+    assert_eq!(send(&mut device, "last\n"), format!("last\r\n{} = {}\r\n", roll.sum, dice.join(" ")));
+}
+
+#[test]
+fn settings_can_be_overridden() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "set 2d20\n"), "set 2d20\r\nok\r\n");
+    assert_eq!(device.display().buffer, [TWO, DELTA, TWO, ZERO]);
+    for _ in 0..40 {
+        tick(&mut device);
+    }
+    // the knobs are ignored
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n2d20 (override)\r\n");
+    assert_eq!(send(&mut device, "set 2d1\n"), "set 2d1\r\nerror: invalid argument\r\n");
+    assert_eq!(send(&mut device, "set 25d6\n"), "set 25d6\r\nerror: invalid argument\r\n");
+
+    assert_eq!(send(&mut device, "set pots\n"), "set pots\r\nok\r\n");
+    for _ in 0..40 {
+        tick(&mut device);
+    }
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6\r\n");
+    assert_eq!(device.display().buffer, [0, THREE, DELTA, SIX]);
+}
+
+#[test]
+fn thresholds_can_be_changed() {
+    let mut device = ready_bench();
+
+    assert_eq!(
+        send(&mut device, "threshold\n"),
+        "threshold\r\nforce 40\r\ndisturb 70\r\nbalance 120\r\nsleep 6000\r\nwake 20\r\n",
+    );
+    assert_eq!(send(&mut device, "threshold sleep 100\n"), "threshold sleep 100\r\nok\r\n");
+    assert_eq!(device.thresholds().ticks_to_sleep, 100);
+    assert_eq!(send(&mut device, "threshold wake 300\n"), "threshold wake 300\r\nerror: invalid argument\r\n");
+
+    for _ in 0..120 {
+        tick(&mut device);
+    }
+    assert!(matches!(device.state(), State::Sleeping { .. }));
+}
+
+#[test]
+fn thresholds_leave_room_for_the_counters() {
+    let mut device = ready_bench();
+
+    // the counters go past these thresholds, they would overflow at the largest values
+    assert_eq!(send(&mut device, "threshold disturb 255\n"), "threshold disturb 255\r\nerror: invalid argument\r\n");
+    assert_eq!(send(&mut device, "threshold wake 255\n"), "threshold wake 255\r\nerror: invalid argument\r\n");
+    assert_eq!(send(&mut device, "threshold sleep 65535\n"), "threshold sleep 65535\r\nerror: invalid argument\r\n");
+    assert_eq!(device.thresholds(), &Bench::DEFAULT_THRESHOLDS);
+
+    // the counters stop at the balance threshold
+    assert_eq!(send(&mut device, "threshold balance 255\n"), "threshold balance 255\r\nok\r\n");
+
+    assert_eq!(send(&mut device, "threshold disturb 254\n"), "threshold disturb 254\r\nok\r\n");
+    assert_eq!(send(&mut device, "threshold wake 254\n"), "threshold wake 254\r\nok\r\n");
+    assert_eq!(send(&mut device, "threshold sleep 65534\n"), "threshold sleep 65534\r\nok\r\n");
+    // the idle counter goes past the largest threshold
+    for _ in 0..65_536 {
+        tick(&mut device);
+    }
+    assert!(matches!(device.state(), State::Sleeping { .. }));
+}
+
+#[test]
+fn modifier_can_be_changed() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "modifier +5\n"), "modifier +5\r\nok\r\n");
+    assert_eq!(device.modifier(), 5);
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6+5\r\n");
+    assert_eq!(send(&mut device, "modifier -100\n"), "modifier -100\r\nerror: invalid argument\r\n");
+
+    assert_eq!(send(&mut device, "modifier -18\nroll\n"), "modifier -18\r\nok\r\nroll\r\nok\r\n");
+    for _ in 0..200 {
+        tick(&mut device);
+    }
+    let roll = *device.last_roll().unwrap();
+    let dice: Vec<String> = roll.dice().iter().map(u16::to_string).collect();
+    assert_eq!(send(&mut device, "last\n"), format!("last\r\n{} = {} -18\r\n", roll.total(), dice.join(" ")));
+}
+
+#[test]
+fn advantage_can_be_selected() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "set Hd20\n"), "set Hd20\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\nHd20 (override)\r\n");
+    assert_eq!(device.display().buffer, [HOTEL, DELTA, TWO, ZERO]);
+
+    send(&mut device, "roll\n");
+    for _ in 0..200 {
+        tick(&mut device);
+    }
+    let roll = *device.last_roll().unwrap();
+    let dice: Vec<String> = (0..2)
+        .map(|i| if roll.is_kept(i) { roll.dice[i].to_string() } else { format!("({})", roll.dice[i]) })
+        .collect();
+    assert_eq!(send(&mut device, "last\n"), format!("last\r\n{} = {}\r\n", roll.sum, dice.join(" ")));
+}
+
+#[test]
+fn dice_can_be_dropped() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "keep h2\n"), "keep h2\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6kh2\r\n");
+
+    send(&mut device, "roll\n");
+    for _ in 0..200 {
+        tick(&mut device);
+    }
+    let roll = *device.last_roll().unwrap();
+    let dropped = roll.dropped_dice().next().unwrap();
+    assert_eq!(roll.sum, roll.dice().iter().sum::<u16>() - dropped);
+    assert!(send(&mut device, "last\n").contains(&format!("({})", dropped)));
+}
+
+#[test]
+fn dice_can_explode() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "set 2d6\n"), "set 2d6\r\nok\r\n");
+    assert_eq!(send(&mut device, "explode on\n"), "explode on\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n2d6! (override)\r\n");
+
+    let roll = (0..100).map(|_| roll(&mut device)).find(|roll| roll.exploded != 0).unwrap();
+    let exploded = roll.dice()[roll.exploded.trailing_zeros() as usize];
+    assert!(exploded > 6);
+    assert!(send(&mut device, "last\n").contains(&format!(" {}!", exploded)));
+    assert!(device.display().buffer.iter().all(|symbol| symbol & POINT != 0));
+}
+
+#[test]
+fn successes_can_be_counted() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "count 5 botch\n"), "count 5 botch\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6>=5f1\r\n");
+    // the target is shown in turns with the dice
+    for _ in 0..301 {
+        tick(&mut device);
+    }
+    assert_eq!(device.display().buffer, [BARS | POINT, 0, 0, FIVE]);
+
+    // roll until a 1 cancels a success
+    let roll = (0..100).map(|_| roll(&mut device)).find(|roll| roll.botched != 0 && roll.kept != 0).unwrap();
+    let successes = roll.dice().iter().filter(|d| **d >= 5).count() as i16;
+    let botches = roll.dice().iter().filter(|d| **d == 1).count() as i16;
+    assert_eq!(roll.total(), successes - botches);
+    let last = send(&mut device, "last\n");
+    assert!(last.starts_with(&format!("last\r\n{} = ", roll.total())));
+    assert_eq!(last.matches('(').count(), 3 - successes as usize);
+    assert_eq!(device.display().buffer[0], BARS);
+
+    assert_eq!(send(&mut device, "count off\n"), "count off\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6\r\n");
+}
+
+#[test]
+fn any_side_count_can_be_selected() {
+    let mut device = ready_bench();
+
+    // side counts missing on the knob are selected with its last zone
+    assert_eq!(send(&mut device, "set 2d7\n"), "set 2d7\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n2d7 (override)\r\n");
+    assert_eq!(device.custom_sides(), 7);
+    assert_eq!(send(&mut device, "sides 255\n"), "sides 255\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n2d255 (override)\r\n");
+    assert_eq!(send(&mut device, "sides 1\n"), "sides 1\r\nerror: invalid argument\r\n");
+    assert_eq!(send(&mut device, "sides 1001\n"), "sides 1001\r\nerror: invalid argument\r\n");
+    assert_eq!(send(&mut device, "set 1d1001\n"), "set 1d1001\r\nerror: invalid argument\r\n");
+
+    // three-digit side counts are shown in turns with the quantity
+    assert_eq!(send(&mut device, "set 12d100\n"), "set 12d100\r\nok\r\n");
+    assert_eq!(device.display().buffer, [0, ONE, TWO, DELTA]);
+    for _ in 0..201 {
+        tick(&mut device);
+    }
+    assert_eq!(device.display().buffer, [DELTA, ONE, ZERO, ZERO]);
+}
+
+#[test]
+fn fudge_dice_can_be_selected() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "set 4dF\n"), "set 4dF\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n4dF (override)\r\n");
+    assert_eq!(device.display().buffer, [0, FOUR, DELTA, FOXTROT]);
+
+    // roll until every die is a minus
+    let roll = (0..500).map(|_| roll(&mut device)).find(|roll| roll.dice() == [1; 4]).unwrap();
+    assert_eq!(roll.total(), -4);
+    assert_eq!(send(&mut device, "last\n"), "last\r\n-4 = - - - -\r\n");
+    assert_eq!(device.display().buffer, [0, 0, MINUS, FOUR | POINT]);
+}
+
+#[test]
+fn critical_rolls_are_animated() {
+    let mut device = ready_bench();
+    send(&mut device, "set 1d6\n");
+
+    (0..100).map(|_| roll(&mut device)).find(|roll| roll.dice() == [1]).unwrap();
+    assert_eq!(device.display().buffer, [A; 4]);
+    for _ in 0..20 {
+        tick(&mut device);
+    }
+    assert_eq!(device.display().buffer, [G; 4]);
+    for _ in 0..140 {
+        tick(&mut device);
+    }
+    assert_eq!(device.display().buffer, [0, 0, 0, ONE | POINT]);
+
+    (0..100).map(|_| roll(&mut device)).find(|roll| roll.dice() == [6]).unwrap();
+    assert_eq!(device.display().buffer, [EIGHT | POINT; 4]);
+
+    // the total appears right away when disabled
+    assert_eq!(send(&mut device, "crit off\n"), "crit off\r\nok\r\n");
+    (0..100).map(|_| roll(&mut device)).find(|roll| roll.dice() == [6]).unwrap();
+    assert_eq!(device.display().buffer, [0, 0, 0, SIX | POINT]);
+}
+
+#[test]
+fn low_throws_can_be_rerolled() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "reroll 1\n"), "reroll 1\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6ro<1\r\n");
+    assert_eq!(send(&mut device, "reroll 2 always\n"), "reroll 2 always\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6r<2\r\n");
+    assert_eq!(send(&mut device, "reroll 2 twice\n"), "reroll 2 twice\r\nerror: invalid argument\r\n");
+    assert_eq!(send(&mut device, "reroll off\n"), "reroll off\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6\r\n");
+}
+
+#[test]
+fn dice_can_be_mixed() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "pool 1d8\n"), "pool 1d8\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6+1d8\r\n");
+    assert_eq!(send(&mut device, "pool 0d8\n"), "pool 0d8\r\nerror: invalid argument\r\n");
+    assert_eq!(send(&mut device, "pool 1dF\n"), "pool 1dF\r\nerror: invalid argument\r\n");
+    assert_eq!(send(&mut device, "pool Hd8\n"), "pool Hd8\r\nerror: invalid argument\r\n");
+    // the selected dice and the pool hold up to 20 dice
+    assert_eq!(send(&mut device, "pool 17d6\n"), "pool 17d6\r\nerror: invalid argument\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6+1d8\r\n");
+
+    let roll = roll(&mut device);
+    let subtotals: Vec<i16> = roll.subtotals().collect();
+    assert_eq!(subtotals.iter().sum::<i16>(), roll.total());
+    let last = send(&mut device, "last\n");
+    assert!(last.ends_with(&format!(" (3d6: {}, 1d8: {})\r\n", subtotals[0], subtotals[1])));
+
+    // the pool holds up to three groups
+    assert_eq!(send(&mut device, "pool 2d4\n"), "pool 2d4\r\nok\r\n");
+    assert_eq!(send(&mut device, "pool 1d100\n"), "pool 1d100\r\nok\r\n");
+    assert_eq!(send(&mut device, "pool 1d4\n"), "pool 1d4\r\nerror: invalid argument\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n3d6+1d8+2d4+1d100\r\n");
+
+    // the pool no longer fits along 17 selected dice, they aren't rolled without it
+    send(&mut device, "set 17d6\n");
+    assert_eq!(send(&mut device, "roll\n"), "roll\r\nerror: too many dice\r\n");
+
+    // Fudge dice are rolled on their own
+    send(&mut device, "set 4dF\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n4dF (override)\r\n");
+
+    assert_eq!(send(&mut device, "pool clear\n"), "pool clear\r\nok\r\n");
+    assert_eq!(device.pool(), []);
+}
+
+#[test]
+fn presets_can_be_loaded() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "preset 2d6+1d8!kh2+3\n"), "preset 2d6+1d8!kh2+3\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n2d6+1d8!kh2+3 (override)\r\n");
+    assert_eq!(device.display().buffer, [0, TWO, DELTA, SIX]);
+
+    assert_eq!(send(&mut device, "preset 2d6x\n"), "preset 2d6x\r\nerror: invalid argument\r\n");
+    assert_eq!(send(&mut device, "preset 2d20kl1\n"), "preset 2d20kl1\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n2d20kl1 (override)\r\n");
+}
+
+#[test]
+fn thousand_sided_dice_can_be_rolled() {
+    let mut device = ready_bench();
+
+    assert_eq!(send(&mut device, "preset 1d1000\n"), "preset 1d1000\r\nok\r\n");
+    assert_eq!(send(&mut device, "settings\n"), "settings\r\n1d1000 (override)\r\n");
+    // four-digit side counts leave no room for the "d"
+    assert_eq!(device.display().buffer, [0, 0, ONE, DELTA]);
+    for _ in 0..201 {
+        tick(&mut device);
+    }
+    assert_eq!(device.display().buffer, [ONE, ZERO, ZERO, ZERO]);
+
+    let die = roll(&mut device).dice()[0];
+    assert!((1..=1000).contains(&die));
+    assert_eq!(send(&mut device, "last\n"), format!("last\r\n{0} = {0}\r\n", die));
+
+    // four-digit totals fill the display
+    send(&mut device, "preset 3d1000\n");
+    let roll = (0..100).map(|_| roll(&mut device)).find(|roll| roll.total() >= 1000).unwrap();
+    let total = roll.total() as usize;
+    let digits = [total / 1000, total / 100 % 10, total / 10 % 10, total % 10].map(|d| MAP[d]);
+    assert_eq!(device.display().buffer, [digits[0], digits[1], digits[2], digits[3] | POINT]);
+}
+
+#[test]
+fn longest_replies_are_sent_whole() {
+    let mut device = ready_bench();
+
+    for line in ["set 6d1000", "pool 6d1000", "pool 6d1000", "pool 2d6", "explode on", "keep l1", "modifier -99"] {
+        assert!(send(&mut device, &format!("{}\n", line)).ends_with("ok\r\n"), "{}", line);
+    }
+    roll(&mut device);
+    let reply = send(&mut device, "last\n");
+    assert!(reply.len() > 128, "{}", reply);
+    assert!(reply.ends_with(")\r\n"), "{}", reply);
+}
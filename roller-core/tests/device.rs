@@ -0,0 +1,1072 @@
+mod common;
+
+use common::{bench, tick_near, Bench};
+use roller_core::device::{Group, Mode, Reroll, Roll, State, Throws};
+use roller_core::display::position::{D2, D3, D4};
+use roller_core::display::segment::POINT;
+use roller_core::display::symbol::*;
+use roller_core::entropy::Generator;
+use roller_core::hardware::{Measurement, TickRate};
+use roller_core::notation::Program;
+use roller_core::scales::FUDGE;
+use roller_core::setup::Page;
+
+/// Pot positions selecting 3d6.
+const QUANTITY_3: u16 = 814;
+const QUALITY_6: u16 = 511;
+
+/// Simulates a single timer tick followed by the ADC measurements it triggers.
+fn tick(device: &mut Bench, acc: u16) {
+    tick_with_pots(device, (QUANTITY_3, QUALITY_6), acc);
+}
+
+/// Simulates a single timer tick with the knobs in the specified positions.
+fn tick_with_pots(device: &mut Bench, pots: (u16, u16), acc: u16) {
+    tick_near(device, pots, [acc - 1, 511, 767]);
+}
+
+/// Simulates the device resting on the table for the specified number of ticks.
+fn rest(device: &mut Bench, ticks: usize) {
+    rest_with_pots(device, (QUANTITY_3, QUALITY_6), ticks);
+}
+
+/// Simulates the device being shaken for the specified number of ticks.
+fn shake(device: &mut Bench, ticks: usize) {
+    shake_with_pots(device, (QUANTITY_3, QUALITY_6), ticks);
+}
+
+/// Simulates the device resting with the knobs in the specified positions.
+fn rest_with_pots(device: &mut Bench, pots: (u16, u16), ticks: usize) {
+    for _ in 0..ticks {
+        tick_with_pots(device, pots, 512);
+    }
+}
+
+/// Simulates the device being shaken with the knobs in the specified positions.
+fn shake_with_pots(device: &mut Bench, pots: (u16, u16), ticks: usize) {
+    for i in 0..ticks {
+        tick_with_pots(device, pots, if i / 16 % 2 == 0 { 300 } else { 700 });
+    }
+}
+
+/// Pot position of the setup zone past disadvantage.
+const QUANTITY_SETUP: u16 = 1008;
+
+/// Returns the position of the quality knob in the middle of the step with the specified index when the knob
+/// is divided into `count` steps, e.g. the pages of the settings menu.
+fn step_position(index: usize, count: usize) -> u16 {
+    ((2 * index + 1) * 1024 / (2 * count)) as u16
+}
+
+/// Returns the positions of the knobs that pick the page of the settings menu.
+fn page(page: Page) -> (u16, u16) {
+    let index = Page::ALL.iter().position(|p| *p == page).unwrap();
+    (QUANTITY_SETUP, step_position(index, Page::ALL.len()))
+}
+
+/// Returns the positions of the knobs that pick the value with the specified index on the open page.
+fn value(page: Page, index: usize) -> (u16, u16) {
+    (QUANTITY_SETUP, step_position(index, page.values() as usize))
+}
+
+/// Opens the page of the settings menu, picks the value with the specified index and applies it. The knob is
+/// turned away first, the menu stays on the page it has been closed on until then.
+fn pick(device: &mut Bench, page: Page, index: usize) {
+    let (quantity, quality) = self::page(page);
+    rest_with_pots(device, (quantity, if quality < 512 { 1023 } else { 0 }), 40);
+    rest_with_pots(device, self::page(page), 40);
+    shake_with_pots(device, self::page(page), 150);
+    rest_with_pots(device, value(page, index), 40);
+    shake_with_pots(device, value(page, index), 150);
+    rest_with_pots(device, value(page, index), 40);
+}
+
+#[test]
+fn settings_are_displayed() {
+    let mut device = bench();
+    rest(&mut device, 16);
+
+    assert!(matches!(device.state(), State::Displaying { .. }));
+    assert_eq!(device.display().buffer, [0, THREE, DELTA, SIX]);
+}
+
+#[test]
+fn shaking_rolls_the_dice() {
+    let mut device = bench();
+    rest(&mut device, 40);
+
+    shake(&mut device, 200);
+    assert!(matches!(device.state(), State::Rolling { .. }));
+
+    rest(&mut device, 200);
+    assert!(matches!(device.state(), State::Displaying { .. }));
+
+    let buffer = device.display().buffer;
+    assert_eq!(buffer[3] & POINT, POINT);
+    let digits: Vec<u8> = buffer.iter()
+        .filter(|s| **s != 0)
+        .map(|s| MAP.iter().position(|d| *d == s & !POINT).unwrap() as u8)
+        .collect();
+    let sum = digits.iter().fold(0, |sum, d| sum * 10 + *d as u16);
+    assert!((3..=18).contains(&sum), "unexpected sum {}", sum);
+}
+
+#[test]
+fn idle_device_falls_asleep_and_wakes_up() {
+    let mut device = bench();
+    rest(&mut device, 40 + 200 * 30);
+
+    assert!(matches!(device.state(), State::Sleeping { .. }));
+    assert_eq!(device.timer.changes, [TickRate::Sleeping]);
+
+    shake(&mut device, 100);
+    assert!(matches!(device.state(), State::Rolling { .. }));
+    assert_eq!(device.timer.changes, [TickRate::Sleeping, TickRate::Normal]);
+}
+
+#[test]
+fn measurements_are_taken_every_tick() {
+    let mut device = bench();
+    tick(&mut device, 512);
+
+    assert_eq!(device.sampler.started, [
+        Measurement::PotQuantity,
+        Measurement::PotQuality,
+        Measurement::AccX,
+        Measurement::AccY,
+        Measurement::AccZ,
+    ]);
+    assert_eq!(device.sampler.measuring, None);
+}
+
+#[test]
+fn displays_are_multiplexed_skipping_empty_ones() {
+    let mut device = bench();
+    rest(&mut device, 16);
+
+    device.port.outputs.clear();
+    rest(&mut device, 6);
+    assert_eq!(device.port.outputs, [
+        (THREE, D2),
+        (DELTA, D3),
+        (SIX, D4),
+        (THREE, D2),
+        (DELTA, D3),
+        (SIX, D4),
+    ]);
+}
+
+#[test]
+fn sleeping_device_turns_the_display_off_and_blinks() {
+    let mut device = bench();
+    rest(&mut device, 40 + 200 * 30);
+    assert!(matches!(device.state(), State::Sleeping { .. }));
+    assert_eq!(device.port.current(), Some((0, 0)));
+
+    // the dot appears once every 10 seconds at 50Hz for half a second.
+    device.port.outputs.clear();
+    rest(&mut device, 500);
+    assert_eq!(device.port.outputs, [(POINT, D4)]);
+    rest(&mut device, 25);
+    assert_eq!(device.port.outputs, [(POINT, D4), (0, 0)]);
+}
+
+#[test]
+fn modifier_is_shown_in_turns_with_the_dice() {
+    let mut device = bench();
+    rest(&mut device, 16);
+    device.set_modifier(-2);
+
+    rest(&mut device, 1);
+    assert_eq!(device.display().buffer, [0, THREE, DELTA, SIX]);
+    rest(&mut device, 300);
+    assert_eq!(device.display().buffer, [0, 0, MINUS, TWO]);
+    rest(&mut device, 150);
+    assert_eq!(device.display().buffer, [0, THREE, DELTA, SIX]);
+}
+
+#[test]
+fn modifier_is_added_to_the_result() {
+    let mut device = bench();
+    rest(&mut device, 40);
+    device.set_modifier(-20);
+
+    shake(&mut device, 200);
+    rest(&mut device, 200);
+    assert!(matches!(device.state(), State::Displaying { .. }));
+
+    let roll = device.last_roll().unwrap();
+    assert_eq!(roll.total(), roll.sum as i16 - 20);
+    assert!((-17..=-2).contains(&roll.total()));
+    assert!(device.display().buffer.contains(&MINUS));
+}
+
+#[test]
+fn advantage_keeps_the_higher_die_and_shows_both() {
+    // past 1d20 on the quantity knob, d20 on the quality knob
+    let pots = (953, 153);
+    let mut device = bench();
+    for _ in 0..40 {
+        tick_with_pots(&mut device, pots, 512);
+    }
+    assert_eq!(device.display().buffer, [HOTEL, DELTA, TWO, ZERO]);
+
+    for i in 0..200 {
+        tick_with_pots(&mut device, pots, if i / 16 % 2 == 0 { 300 } else { 700 });
+    }
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick_with_pots(&mut device, pots, 512);
+    }
+
+    let roll = *device.last_roll().unwrap();
+    assert_eq!(roll.mode, Mode::KeepHighest(1));
+    assert_eq!(roll.dice().len(), 2);
+    assert_eq!(roll.sum, *roll.dice().iter().max().unwrap() as u16);
+
+    let total = device.display().buffer;
+    assert_eq!(total[3] & POINT, POINT);
+
+    for _ in 0..401 {
+        tick_with_pots(&mut device, pots, 512);
+    }
+    let digits = |n: u16| if n >= 10 { [MAP[(n / 10) as usize], MAP[(n % 10) as usize]] } else { [0, MAP[n as usize]] };
+    let (left, right) = (digits(roll.dice[0]), digits(roll.dice[1]));
+    assert_eq!(device.display().buffer, [left[0], left[1] | POINT, right[0], right[1]]);
+
+    for _ in 0..300 {
+        tick_with_pots(&mut device, pots, 512);
+    }
+    assert_eq!(device.display().buffer, total);
+    // stays on the total
+    for _ in 0..1000 {
+        tick_with_pots(&mut device, pots, 512);
+    }
+    assert_eq!(device.display().buffer, total);
+}
+
+/// Returns the throws of `quantity` d6 starting with the specified ones.
+fn throws(quantity: u8, exploding: bool, dice: &[u16]) -> Throws {
+    let mut throws = Throws::new(quantity, 6, exploding, Reroll::Never);
+    for d in dice {
+        throws.put(*d);
+    }
+    throws
+}
+
+#[test]
+fn highest_dice_are_kept() {
+    let dice = [3, 6, 1, 6, 2];
+
+    let roll = Roll::new(&throws(4, false, &dice[..4]), Mode::KeepHighest(3), 1).unwrap();
+    assert_eq!(roll.sum, 15);
+    assert_eq!(roll.total(), 16);
+    assert_eq!(roll.dropped_dice().collect::<Vec<_>>(), [1]);
+
+    let roll = Roll::new(&throws(5, false, &dice), Mode::KeepLowest(2), 0).unwrap();
+    assert_eq!(roll.sum, 3);
+    assert_eq!(roll.kept_dice().collect::<Vec<_>>(), [1, 2]);
+
+    // only the first of the equal dice is kept
+    let roll = Roll::new(&throws(4, false, &dice[..4]), Mode::KeepHighest(1), 0).unwrap();
+    assert_eq!(roll.kept, 0b0010);
+
+    // keeping more dice than rolled keeps all of them
+    let roll = Roll::new(&throws(2, false, &dice[..2]), Mode::KeepLowest(5), 0).unwrap();
+    assert_eq!(roll.sum, 9);
+
+    assert_eq!(Roll::new(&throws(6, false, &dice), Mode::Sum, 0), None);
+}
+
+#[test]
+fn only_single_dice_and_pairs_are_critical() {
+    assert_eq!(Roll::new(&throws(1, false, &[6]), Mode::Sum, 0).unwrap().critical(), Some(true));
+    assert_eq!(Roll::new(&throws(2, false, &[1, 4]), Mode::KeepLowest(1), 0).unwrap().critical(), Some(false));
+
+    // the highest of more dice is often the maximum face
+    assert_eq!(Roll::new(&throws(4, false, &[6, 2, 3, 1]), Mode::KeepHighest(1), 0).unwrap().critical(), None);
+    assert_eq!(Roll::new(&throws(3, false, &[1, 2, 3]), Mode::KeepLowest(1), 0).unwrap().critical(), None);
+}
+
+#[test]
+fn successes_are_counted() {
+    let dice = throws(5, false, &[5, 1, 6, 2, 1]);
+
+    let roll = Roll::new(&dice, Mode::Successes { target: 5, botches: false }, 3).unwrap();
+    assert_eq!(roll.kept, 0b00101);
+    assert_eq!(roll.total(), 2);
+    // the modifier isn't added to the number of successes
+    assert_eq!(roll.modifier, 0);
+
+    let roll = Roll::new(&dice, Mode::Successes { target: 5, botches: true }, 0).unwrap();
+    assert_eq!(roll.botched, 0b10010);
+    assert_eq!(roll.total(), 0);
+}
+
+#[test]
+fn low_throws_are_rerolled() {
+    let mut once = Throws::new(3, 6, false, Reroll::Once(2));
+    for d in [1, 2, 2, 5, 6] {
+        once.put(d);
+    }
+    assert_eq!(once.dice(), [2, 5, 6]);
+
+    let mut always = Throws::new(2, 6, false, Reroll::Always(2));
+    for d in [1, 2, 1, 3, 2, 6] {
+        always.put(d);
+    }
+    assert_eq!(always.dice(), [3, 6]);
+
+    // there's no face above 6 to wait for
+    let mut never = Throws::new(1, 6, false, Reroll::Always(6));
+    never.put(4);
+    assert_eq!(never.dice(), [4]);
+
+    // the throws following an explosion are kept
+    let mut exploding = Throws::new(1, 6, true, Reroll::Once(2));
+    for d in [6, 1] {
+        exploding.put(d);
+    }
+    assert_eq!(exploding.dice(), [7]);
+
+    // shown in turns with the dice
+    let mut device = bench();
+    rest(&mut device, 40);
+    device.set_reroll(Reroll::Always(2));
+    rest(&mut device, 301);
+    assert_eq!(device.display().buffer, [0, ROMEO, ROMEO, TWO]);
+}
+
+#[test]
+fn fudge_dice_add_up_to_a_signed_total() {
+    let mut throws = Throws::new(4, FUDGE, false, Reroll::Never);
+    for d in [1, 1, 2, 3] {
+        throws.put(d);
+    }
+    let roll = Roll::new(&throws, Mode::Sum, 0).unwrap();
+    assert!(roll.fudge);
+    assert_eq!(roll.total(), -1);
+    assert_eq!(Roll::new(&throws, Mode::Sum, 3).unwrap().total(), 2);
+
+    // 4dF
+    let mut device = bench();
+    for _ in 0..16 {
+        tick_with_pots(&mut device, (767, 883), 512);
+    }
+    assert_eq!(device.display().buffer, [0, FOUR, DELTA, FOXTROT]);
+}
+
+#[test]
+fn dice_explode_on_the_maximum_face() {
+    let roll = Roll::new(&throws(3, true, &[6, 6, 2, 4, 6, 1]), Mode::Sum, 0).unwrap();
+    assert_eq!(roll.dice(), [14, 4, 7]);
+    assert_eq!(roll.exploded, 0b101);
+    assert_eq!(roll.sum, 25);
+
+    // the dice don't explode unless enabled
+    assert_eq!(throws(2, false, &[6, 6]).dice(), [6, 6]);
+
+    // the number of explosions per roll is limited
+    let capped = throws(2, true, &[6; 12]);
+    assert_eq!(capped.explosions(), Throws::MAX_EXPLOSIONS);
+    assert_eq!(capped.dice(), [66, 6]);
+
+    // a die stops exploding before it gets too large for the display
+    let mut large = Throws::new(1, 1000, true, Reroll::Never);
+    for _ in 0..9 {
+        large.put(1000);
+    }
+    assert_eq!(large.dice(), [9000]);
+    assert_eq!(large.explosions(), 8);
+
+    // wide dice explode too
+    let mut percentile = Throws::new(1, 100, true, Reroll::Never);
+    for d in [100, 5] {
+        percentile.put(d);
+    }
+    assert_eq!(percentile.dice(), [105]);
+
+    // a complete set of throws starts over
+    let mut again = throws(1, true, &[6, 2]);
+    again.put(3);
+    assert_eq!(again.dice(), [3]);
+    assert_eq!(again.explosions(), 0);
+}
+
+#[test]
+fn dropped_dice_are_shown_after_the_total() {
+    let mut device = bench();
+    rest(&mut device, 40);
+    device.set_mode(Mode::KeepHighest(1));
+    device.set_show_dropped(true);
+
+    // the mode is shown in turns with the dice
+    rest(&mut device, 301);
+    assert_eq!(device.display().buffer, [0, 0, HOTEL, ONE]);
+
+    shake(&mut device, 200);
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick(&mut device, 512);
+    }
+    let roll = *device.last_roll().unwrap();
+    let total = device.display().buffer;
+
+    let dropped: Vec<u16> = roll.dropped_dice().collect();
+    rest(&mut device, 401);
+    assert_eq!(device.display().buffer, [0, MAP[dropped[0] as usize] | POINT, 0, MAP[dropped[1] as usize]]);
+    rest(&mut device, 300);
+    assert_eq!(device.display().buffer, total);
+}
+
+#[test]
+fn every_die_is_shown_after_the_total() {
+    let mut device = bench();
+    rest(&mut device, 40);
+    device.set_show_dice(true);
+
+    shake(&mut device, 200);
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick(&mut device, 512);
+    }
+    let roll = *device.last_roll().unwrap();
+    let total = device.display().buffer;
+
+    rest(&mut device, 400);
+    for (i, d) in roll.dice().iter().enumerate() {
+        rest(&mut device, 1);
+        assert_eq!(device.display().buffer, [0, MAP[i + 1] | POINT, 0, MAP[*d as usize]]);
+        rest(&mut device, 299);
+    }
+    rest(&mut device, 1);
+    assert_eq!(device.display().buffer, total);
+    // stays on the total
+    rest(&mut device, 1000);
+    assert_eq!(device.display().buffer, total);
+}
+
+#[test]
+fn indexes_that_do_not_fit_along_wide_dice_are_shown_first() {
+    let mut device = bench();
+    rest(&mut device, 40);
+    let mut program = Program::new();
+    program.add(Group { quantity: 12, sides: 1000 });
+    assert!(device.load(&program));
+    device.set_show_dice(true);
+
+    shake(&mut device, 200);
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick(&mut device, 512);
+    }
+    let roll = *device.last_roll().unwrap();
+    assert!(roll.dice()[9..].iter().any(|d| *d >= 100));
+    let total = device.display().buffer;
+    // the number aligned to the right
+    let number = |n: usize| {
+        let mut buffer = [0; 4];
+        for (i, digit) in n.to_string().bytes().rev().enumerate() {
+            buffer[3 - i] = MAP[(digit - b'0') as usize];
+        }
+        buffer
+    };
+
+    // the index followed by a dot, left of the value
+    let index = |i: usize, width: usize, value: [u8; 4]| {
+        let mut page = value;
+        page[..width].copy_from_slice(&number(i)[4 - width..]);
+        page[width - 1] |= POINT;
+        page
+    };
+
+    rest(&mut device, 400);
+    for (i, d) in roll.dice().iter().enumerate() {
+        let value = number(*d as usize);
+        let (index_len, value_len) = ((i + 1).to_string().len(), d.to_string().len());
+        rest(&mut device, 1);
+        if index_len + value_len <= 4 {
+            // e.g. "3.872" or "12.72"
+            assert_eq!(device.display().buffer, index(i + 1, if value_len == 3 { 1 } else { 2 }, value));
+        } else {
+            // e.g. "12." followed by "872"
+            assert_eq!(device.display().buffer, index(i + 1, 2, [0; 4]));
+            rest(&mut device, 100);
+            assert_eq!(device.display().buffer, value);
+        }
+        rest(&mut device, 299);
+    }
+    rest(&mut device, 1);
+    assert_eq!(device.display().buffer, total);
+}
+
+#[test]
+fn mixed_dice_have_subtotals() {
+    let mut throws = Throws::new(2, 6, true, Reroll::Never);
+    throws.add(Group { quantity: 1, sides: 8 });
+    for d in [3, 6, 2] {
+        throws.put(d);
+    }
+    assert_eq!(throws.group(), 1);
+    // a 6 doesn't explode on a d8
+    throws.put(6);
+    assert_eq!(throws.dice(), [3, 8, 6]);
+
+    let roll = Roll::new(&throws, Mode::KeepHighest(2), 2).unwrap();
+    assert_eq!(roll.subtotals().collect::<Vec<_>>(), [8, 6]);
+    assert_eq!(roll.total(), 16);
+    assert_eq!(roll.sides(2), 8);
+
+    // an 8 is the maximum face of the d8 only
+    let mut throws = Throws::new(1, 20, false, Reroll::Never);
+    throws.add(Group { quantity: 1, sides: 8 });
+    for d in [2, 8] {
+        throws.put(d);
+    }
+    assert_eq!(Roll::new(&throws, Mode::KeepHighest(1), 0).unwrap().critical(), Some(true));
+
+    // the subtotals are shown after the total
+    let mut device = bench();
+    rest(&mut device, 40);
+    device.add_to_pool(Group { quantity: 1, sides: 8 });
+    rest(&mut device, 301);
+    assert_eq!(device.display().buffer, [0, ONE, DELTA, EIGHT]);
+
+    shake(&mut device, 200);
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick(&mut device, 512);
+    }
+    let roll = *device.last_roll().unwrap();
+    let total = device.display().buffer;
+    let subtotals: Vec<i16> = roll.subtotals().collect();
+    let number = |n: i16| [0, 0, if n >= 10 { MAP[n as usize / 10] } else { 0 }, MAP[n as usize % 10]];
+
+    rest(&mut device, 401);
+    assert_eq!(device.display().buffer, [0, THREE, DELTA, SIX]);
+    rest(&mut device, 150);
+    assert_eq!(device.display().buffer, number(subtotals[0]));
+    rest(&mut device, 300);
+    assert_eq!(device.display().buffer, [0, ONE, DELTA, EIGHT]);
+    rest(&mut device, 150);
+    assert_eq!(device.display().buffer, number(subtotals[1]));
+    rest(&mut device, 300);
+    assert_eq!(device.display().buffer, total);
+}
+
+/// Simulates a timer tick with the accelerometer axes reading the specified values.
+fn tick_with_acc(device: &mut Bench, [x, y, z]: [u16; 3]) {
+    common::tick(device, |m| match m {
+        Measurement::PotQuantity => QUANTITY_3,
+        Measurement::PotQuality => QUALITY_6,
+        Measurement::AccX => x,
+        Measurement::AccY => y,
+        Measurement::AccZ => z,
+    });
+}
+
+#[test]
+fn unhealthy_accelerometer_refuses_to_roll() {
+    // the Y axis is disconnected and always reads the same, or reads the same but for a glitch now and then
+    let streams: [fn(usize) -> u16; 2] = [|_| 512, |i| if i % 100 == 50 { 513 } else { 512 }];
+    for y in streams {
+        let mut device = bench();
+        // the shake is long enough for a whole window of the adaptive proportion test
+        for i in 0..840 {
+            let noise = (i % 3) as u16;
+            let x = match i {
+                40..640 if i / 16 % 2 == 0 => 300,
+                40..640 => 700,
+                _ => 511,
+            };
+            tick_with_acc(&mut device, [x + noise, y(i), 767 + noise]);
+            if i == 639 {
+                assert!(matches!(device.state(), State::Rolling { .. }));
+            }
+        }
+        assert!(matches!(device.state(), State::Displaying { .. }));
+        assert_eq!(device.display().buffer, [ECHO, ROMEO, ROMEO, 0]);
+        assert!(device.last_roll().is_none());
+
+        // the axis is healthy again, but the window it failed is still in progress during the next roll
+        shake(&mut device, 600);
+        rest(&mut device, 200);
+        assert_eq!(device.display().buffer, [ECHO, ROMEO, ROMEO, 0]);
+        assert!(device.last_roll().is_none());
+
+        // the dice are rolled again once the axis has been healthy for a whole window
+        shake(&mut device, 600);
+        rest(&mut device, 200);
+        assert!(device.last_roll().is_some());
+    }
+}
+
+/// Shakes the device with the knobs in the specified positions, returns how many ticks it takes to show the
+/// result once the device rests.
+fn ticks_to_show(pots: (u16, u16)) -> usize {
+    let mut device = bench();
+    for i in 0..240 {
+        let acc = match i {
+            40..240 if i / 16 % 2 == 0 => 300,
+            40..240 => 700,
+            _ => 512,
+        };
+        tick_with_pots(&mut device, pots, acc);
+    }
+    assert!(matches!(device.state(), State::Rolling { .. }));
+
+    let mut ticks = 0;
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick_with_pots(&mut device, pots, 512);
+        ticks += 1;
+    }
+    ticks
+}
+
+#[test]
+fn complete_dice_are_kept_once_the_device_rests() {
+    // 10d6 takes as long as a single die
+    assert_eq!(ticks_to_show((488, QUALITY_6)), ticks_to_show((907, QUALITY_6)));
+}
+
+#[test]
+fn rolls_wait_for_fresh_bits() {
+    let mut device = bench();
+    // the lowest bits of the accelerometer never change, there's nothing to whiten
+    for i in 0..440 {
+        let noise = 2 * (i % 3) as u16;
+        let x = match i {
+            40..240 if i / 16 % 2 == 0 => 300,
+            40..240 => 700,
+            _ => 510,
+        };
+        tick_with_acc(&mut device, [x + noise, 510 + noise, 766 + noise]);
+    }
+    assert!(matches!(device.state(), State::Rolling { .. }));
+    assert_eq!(device.entropy().harvested(), 0);
+
+    // every die takes its own fresh bits
+    rest(&mut device, 200);
+    assert!(matches!(device.state(), State::Displaying { .. }));
+    let entropy = device.entropy();
+    assert!(entropy.consumed() >= 3 * Generator::RESEED_BITS as u16);
+    assert_eq!(entropy.harvested() - entropy.consumed(), entropy.available() as u16);
+}
+
+#[test]
+fn quiet_axes_pass_while_the_roll_waits_at_rest() {
+    let mut device = bench();
+    // no bits are harvested during the shake
+    for i in 0..240 {
+        let noise = 2 * (i % 3) as u16;
+        let x = match i {
+            40..240 if i / 16 % 2 == 0 => 300,
+            40..240 => 700,
+            _ => 510,
+        };
+        tick_with_acc(&mut device, [x + noise, 510 + noise, 766 + noise]);
+    }
+    assert!(matches!(device.state(), State::Rolling { .. }));
+
+    // Y and Z read the same for seconds, only the slow drift of X yields bits
+    for i in 0..1200 {
+        tick_with_acc(&mut device, [512 + (i / 7 % 2) as u16, 512, 768]);
+    }
+    assert!(matches!(device.state(), State::Displaying { .. }));
+    assert!(device.last_roll().is_some());
+}
+
+#[test]
+fn starved_roll_shows_the_failure() {
+    let mut device = bench();
+    // the lowest bits of the accelerometer never change, the roll gives up a while after the device rests
+    for i in 0..840 {
+        let noise = 2 * (i % 3) as u16;
+        let x = match i {
+            40..240 if i / 16 % 2 == 0 => 300,
+            40..240 => 700,
+            _ => 510,
+        };
+        tick_with_acc(&mut device, [x + noise, 510 + noise, 766 + noise]);
+        if i == 440 {
+            assert!(matches!(device.state(), State::Rolling { .. }));
+        }
+    }
+    assert!(matches!(device.state(), State::Displaying { .. }));
+    assert_eq!(device.display().buffer, [ECHO, ROMEO, ROMEO, 0]);
+    assert!(device.last_roll().is_none());
+}
+
+#[test]
+fn modifier_is_picked_in_the_settings_menu() {
+    let mut device = bench();
+    rest(&mut device, 40);
+
+    // past disadvantage, the quality knob picks a page, shown with the current value
+    rest_with_pots(&mut device, page(Page::Modifier), 40);
+    assert!(matches!(device.state(), State::Setup { .. }));
+    assert_eq!(device.display().buffer, [ONE | POINT, 0, 0, ZERO]);
+
+    // a shake opens the page rather than rolling, on the value the knob is on
+    shake_with_pots(&mut device, page(Page::Modifier), 150);
+    rest_with_pots(&mut device, page(Page::Modifier), 40);
+    assert!(matches!(device.state(), State::Setup { .. }));
+    assert_eq!(device.display().buffer[0], ONE);
+
+    // the value isn't applied before another shake
+    let plus_3 = value(Page::Modifier, 23);
+    rest_with_pots(&mut device, plus_3, 40);
+    assert_eq!(device.display().buffer, [ONE, 0, PLUS, THREE]);
+    assert_eq!(device.modifier(), 0);
+
+    shake_with_pots(&mut device, plus_3, 150);
+    rest_with_pots(&mut device, plus_3, 40);
+    assert_eq!(device.modifier(), 3);
+    assert_eq!(device.display().buffer, [ONE | POINT, 0, PLUS, THREE]);
+
+    // leaving the setup zone shows the dice again, the modifier in turns with them
+    rest(&mut device, 40);
+    assert!(matches!(device.state(), State::Displaying { .. }));
+    assert_eq!(device.display().buffer, [0, THREE, DELTA, SIX]);
+    rest(&mut device, 300);
+    assert_eq!(device.display().buffer, [0, 0, PLUS, THREE]);
+
+    shake(&mut device, 200);
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick(&mut device, 512);
+    }
+    assert_eq!(device.last_roll().unwrap().modifier, 3);
+
+    // negative modifiers
+    pick(&mut device, Page::Modifier, 15);
+    assert_eq!(device.modifier(), -5);
+    assert_eq!(device.display().buffer, [ONE | POINT, 0, MINUS, FIVE]);
+}
+
+#[test]
+fn idle_settings_menu_falls_asleep_and_wakes_up_into_it() {
+    let mut device = bench();
+    rest_with_pots(&mut device, page(Page::Modifier), 40 + 200 * 30);
+    assert!(matches!(device.state(), State::Sleeping { .. }));
+
+    shake_with_pots(&mut device, page(Page::Modifier), 100);
+    assert!(matches!(device.state(), State::Setup { .. }));
+    assert_eq!(device.timer.changes, [TickRate::Sleeping, TickRate::Normal]);
+    assert_eq!(device.display().buffer, [ONE | POINT, 0, 0, ZERO]);
+}
+
+#[test]
+fn keep_mode_is_picked_in_the_settings_menu() {
+    let mut device = bench();
+    rest(&mut device, 40);
+
+    // keeping all the dice is in the middle of the page, the lowest dice before it and the highest after it
+    rest_with_pots(&mut device, page(Page::Keep), 40);
+    assert_eq!(device.display().buffer, [TWO | POINT, 0, 0, MINUS]);
+
+    pick(&mut device, Page::Keep, 21);
+    assert_eq!(device.mode(), Mode::KeepHighest(2));
+    assert_eq!(device.display().buffer, [TWO | POINT, 0, HOTEL, TWO]);
+    pick(&mut device, Page::Keep, 0);
+    assert_eq!(device.mode(), Mode::KeepLowest(19));
+    assert_eq!(device.display().buffer, [TWO | POINT, LIMA, ONE, NINE]);
+    pick(&mut device, Page::Keep, 18);
+    assert_eq!(device.mode(), Mode::KeepLowest(1));
+
+    rest_with_pots(&mut device, page(Page::Dropped), 40);
+    assert_eq!(device.display().buffer, [THREE | POINT, OSCAR, FOXTROT, FOXTROT]);
+    pick(&mut device, Page::Dropped, 1);
+    assert!(device.show_dropped());
+    assert_eq!(device.display().buffer, [THREE | POINT, 0, OSCAR, NOVEMBER]);
+
+    // the mode is shown in turns with the dice once the knob is back on them
+    rest(&mut device, 320);
+    assert_eq!(device.display().buffer, [0, 0, LIMA, ONE]);
+
+    pick(&mut device, Page::Keep, 19);
+    assert_eq!(device.mode(), Mode::Sum);
+    pick(&mut device, Page::Dropped, 0);
+    assert!(!device.show_dropped());
+}
+
+#[test]
+fn exploding_dice_are_picked_in_the_settings_menu() {
+    let mut device = bench();
+    rest(&mut device, 40);
+
+    rest_with_pots(&mut device, page(Page::Exploding), 40);
+    assert_eq!(device.display().buffer, [FOUR | POINT, OSCAR, FOXTROT, FOXTROT]);
+    pick(&mut device, Page::Exploding, 1);
+    assert!(device.exploding());
+    assert_eq!(device.display().buffer, [FOUR | POINT, 0, OSCAR, NOVEMBER]);
+
+    // 6s are thrown again until the roll has exploded
+    rest(&mut device, 40);
+    while device.last_roll().is_none_or(|roll| roll.exploded == 0) {
+        shake(&mut device, 200);
+        while matches!(device.state(), State::Rolling { .. }) {
+            tick(&mut device, 512);
+        }
+    }
+    let roll = device.last_roll().unwrap();
+    assert!(roll.dice().iter().any(|d| *d > 6));
+    assert!(device.display().buffer.iter().all(|s| s & POINT != 0));
+
+    pick(&mut device, Page::Exploding, 0);
+    assert!(!device.exploding());
+}
+
+#[test]
+fn successes_are_picked_in_the_settings_menu() {
+    let mut device = bench();
+    rest(&mut device, 40);
+
+    rest_with_pots(&mut device, page(Page::Count), 40);
+    assert_eq!(device.display().buffer, [FIVE | POINT, 0, 0, MINUS]);
+    pick(&mut device, Page::Count, 6);
+    assert_eq!(device.mode(), Mode::Successes { target: 7, botches: false });
+    assert_eq!(device.display().buffer, [FIVE | POINT, BARS, 0, SEVEN]);
+    pick(&mut device, Page::Count, 38);
+    assert_eq!(device.mode(), Mode::Successes { target: 20, botches: true });
+    assert_eq!(device.display().buffer, [FIVE | POINT, BARS | POINT, TWO, ZERO]);
+    pick(&mut device, Page::Count, 23);
+    assert_eq!(device.mode(), Mode::Successes { target: 5, botches: true });
+
+    // keeping all the dice doesn't stop counting them
+    pick(&mut device, Page::Keep, 19);
+    assert_eq!(device.mode(), Mode::Successes { target: 5, botches: true });
+
+    rest(&mut device, 40);
+    shake(&mut device, 200);
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick(&mut device, 512);
+    }
+    assert!(matches!(device.last_roll().unwrap().mode, Mode::Successes { target: 5, botches: true }));
+    assert_eq!(device.display().buffer[0], BARS);
+
+    // a keep mode replaces counting
+    pick(&mut device, Page::Keep, 22);
+    assert_eq!(device.mode(), Mode::KeepHighest(3));
+    assert_eq!(device.display().buffer, [TWO | POINT, 0, HOTEL, THREE]);
+    pick(&mut device, Page::Count, 0);
+    assert_eq!(device.mode(), Mode::KeepHighest(3));
+    pick(&mut device, Page::Count, 1);
+    pick(&mut device, Page::Count, 0);
+    assert_eq!(device.mode(), Mode::Sum);
+}
+
+#[test]
+fn every_die_is_shown_once_picked_in_the_settings_menu() {
+    let mut device = bench();
+    rest(&mut device, 40);
+
+    rest_with_pots(&mut device, page(Page::Dice), 40);
+    assert_eq!(device.display().buffer, [SIX | POINT, OSCAR, FOXTROT, FOXTROT]);
+    pick(&mut device, Page::Dice, 1);
+    assert!(device.show_dice());
+    assert_eq!(device.display().buffer, [SIX | POINT, 0, OSCAR, NOVEMBER]);
+
+    rest(&mut device, 40);
+    shake(&mut device, 200);
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick(&mut device, 512);
+    }
+    let roll = *device.last_roll().unwrap();
+    rest(&mut device, 401);
+    assert_eq!(device.display().buffer, [0, ONE | POINT, 0, MAP[roll.dice[0] as usize]]);
+
+    pick(&mut device, Page::Dice, 0);
+    assert!(!device.show_dice());
+}
+
+#[test]
+fn custom_sides_are_picked_in_the_settings_menu() {
+    let mut device = bench();
+    // the custom quality zone
+    let custom = (QUANTITY_3, 1000);
+    rest_with_pots(&mut device, custom, 40);
+    assert_eq!(device.display().buffer, [THREE, DELTA, THREE, ZERO]);
+
+    rest_with_pots(&mut device, page(Page::Sides), 40);
+    assert_eq!(device.display().buffer, [SEVEN | POINT, DELTA, THREE, ZERO]);
+    pick(&mut device, Page::Sides, 22);
+    assert_eq!(device.custom_sides(), 24);
+    assert_eq!(device.display().buffer, [SEVEN | POINT, DELTA, TWO, FOUR]);
+    pick(&mut device, Page::Sides, 98);
+    assert_eq!(device.custom_sides(), 100);
+    assert_eq!(device.display().buffer, [SEVEN | POINT, ONE, ZERO, ZERO]);
+    pick(&mut device, Page::Sides, 0);
+    assert_eq!(device.custom_sides(), 2);
+    assert_eq!(device.display().buffer, [SEVEN | POINT, 0, DELTA, TWO]);
+
+    rest_with_pots(&mut device, custom, 40);
+    assert_eq!(device.display().buffer, [0, THREE, DELTA, TWO]);
+    shake_with_pots(&mut device, custom, 200);
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick_with_pots(&mut device, custom, 512);
+    }
+    assert!(device.last_roll().unwrap().dice().iter().all(|d| (1..=2).contains(d)));
+}
+
+#[test]
+fn critical_animation_is_turned_off_in_the_settings_menu() {
+    let mut device = bench();
+    rest(&mut device, 40);
+
+    rest_with_pots(&mut device, page(Page::Critical), 40);
+    assert_eq!(device.display().buffer, [EIGHT | POINT, 0, OSCAR, NOVEMBER]);
+    pick(&mut device, Page::Critical, 0);
+    assert!(!device.show_critical());
+    assert_eq!(device.display().buffer, [EIGHT | POINT, OSCAR, FOXTROT, FOXTROT]);
+    pick(&mut device, Page::Critical, 1);
+    assert!(device.show_critical());
+}
+
+#[test]
+fn rerolls_are_picked_in_the_settings_menu() {
+    let mut device = bench();
+    rest(&mut device, 40);
+
+    rest_with_pots(&mut device, page(Page::Reroll), 40);
+    assert_eq!(device.display().buffer, [NINE | POINT, 0, 0, MINUS]);
+    pick(&mut device, Page::Reroll, 2);
+    assert_eq!(device.reroll(), Reroll::Once(2));
+    assert_eq!(device.display().buffer, [NINE | POINT, 0, ROMEO, TWO]);
+    pick(&mut device, Page::Reroll, 11);
+    assert_eq!(device.reroll(), Reroll::Always(2));
+    assert_eq!(device.display().buffer, [NINE | POINT, ROMEO, ROMEO, TWO]);
+
+    // 1s and 2s never stay
+    rest(&mut device, 40);
+    shake(&mut device, 200);
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick(&mut device, 512);
+    }
+    assert!(device.last_roll().unwrap().dice().iter().all(|d| *d > 2));
+
+    pick(&mut device, Page::Reroll, 0);
+    assert_eq!(device.reroll(), Reroll::Never);
+}
+
+#[test]
+fn pool_is_filled_and_emptied_in_the_settings_menu() {
+    let mut device = bench();
+    rest(&mut device, 40);
+
+    // the knob passes 1, advantage and disadvantage on the way to the menu, the selected dice stay the same
+    rest_with_pots(&mut device, (907, 418), 200);
+    rest_with_pots(&mut device, (QUANTITY_SETUP, 418), 40);
+    pick(&mut device, Page::Pool, 1);
+    assert_eq!(device.pool(), [Group { quantity: 1, sides: 8 }]);
+    assert_eq!(device.display().buffer, [PAPA | POINT, 0, 0, ONE]);
+
+    rest_with_pots(&mut device, (861, QUALITY_6), 200);
+    rest_with_pots(&mut device, (QUANTITY_SETUP, QUALITY_6), 40);
+    pick(&mut device, Page::Pool, 1);
+    assert_eq!(device.pool(), [Group { quantity: 1, sides: 8 }, Group { quantity: 2, sides: 6 }]);
+    assert_eq!(device.display().buffer, [PAPA | POINT, 0, 0, TWO]);
+
+    // the pool is rolled along with the selected dice
+    rest(&mut device, 40);
+    shake(&mut device, 200);
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick(&mut device, 512);
+    }
+    assert_eq!(device.last_roll().unwrap().groups(), [
+        Group { quantity: 3, sides: 6 }, Group { quantity: 1, sides: 8 }, Group { quantity: 2, sides: 6 },
+    ]);
+
+    pick(&mut device, Page::Pool, 0);
+    assert_eq!(device.pool(), []);
+    assert_eq!(device.display().buffer, [PAPA | POINT, 0, 0, ZERO]);
+}
+
+#[test]
+fn pool_that_no_longer_fits_is_not_rolled() {
+    let mut device = bench();
+    rest(&mut device, 40);
+    assert!(device.add_to_pool(Group { quantity: 1, sides: 8 }));
+
+    // 20d6 leave no room for the d8, the error glyph is shown instead of it
+    rest_with_pots(&mut device, (20, QUALITY_6), 40);
+    assert_eq!(device.selected(), Some(Group { quantity: 20, sides: 6 }));
+    assert_eq!(device.pool(), [Group { quantity: 1, sides: 8 }]);
+    assert_eq!(device.display().buffer, [TWO, ZERO, DELTA, SIX]);
+    rest_with_pots(&mut device, (20, QUALITY_6), 300);
+    assert_eq!(device.display().buffer, [ECHO, ROMEO, ROMEO, 0]);
+
+    // a shake shows the error rather than rolling without the d8
+    shake_with_pots(&mut device, (20, QUALITY_6), 200);
+    assert!(matches!(device.state(), State::Displaying { .. }));
+    assert_eq!(device.last_roll(), None);
+    assert_eq!(device.display().buffer, [ECHO, ROMEO, ROMEO, 0]);
+
+    // the pool is rolled again along with fewer dice
+    rest_with_pots(&mut device, (QUANTITY_3, QUALITY_6), 40);
+    shake(&mut device, 200);
+    while matches!(device.state(), State::Rolling { .. }) {
+        tick(&mut device, 512);
+    }
+    assert_eq!(device.last_roll().unwrap().groups(), [Group { quantity: 3, sides: 6 }, Group { quantity: 1, sides: 8 }]);
+}
+
+#[test]
+fn programs_are_loaded_with_the_checks_of_the_pool() {
+    let mut device = bench();
+    let program = |groups: &[Group]| {
+        let mut program = Program::new();
+        groups.iter().for_each(|group| assert!(program.add(*group)));
+        program
+    };
+
+    let rejected = [
+        [Group { quantity: 2, sides: 6 }, Group { quantity: 0, sides: 8 }],
+        [Group { quantity: 2, sides: 6 }, Group { quantity: 1, sides: 1 }],
+        [Group { quantity: 2, sides: 6 }, Group { quantity: 1, sides: FUDGE }],
+        [Group { quantity: 4, sides: FUDGE }, Group { quantity: 1, sides: 6 }],
+        [Group { quantity: 12, sides: 6 }, Group { quantity: 12, sides: 8 }],
+    ];
+    for groups in rejected {
+        assert!(!device.load(&program(&groups)), "{:?}", groups);
+        assert_eq!(device.selected(), None);
+        assert_eq!(device.pool(), []);
+    }
+
+    assert!(device.load(&program(&[Group { quantity: 2, sides: 6 }, Group { quantity: 1, sides: 8 }])));
+    assert_eq!(device.selected(), Some(Group { quantity: 2, sides: 6 }));
+    assert_eq!(device.pool(), [Group { quantity: 1, sides: 8 }]);
+}
+
+#[cfg(feature = "debug_spi")]
+#[test]
+fn full_debug_channel_drops_whole_frames() {
+    use roller_core::protocol::{Decoded, Message};
+
+    // nothing reads the debug channel meanwhile
+    fn tick_unread(device: &mut Bench) {
+        device.timer_interrupt();
+        while device.sampler.measuring.is_some() {
+            device.adc_interrupt(512);
+        }
+    }
+    fn read_frames(device: &mut Bench) -> usize {
+        let mut bytes = Vec::new();
+        while let Some(b) = device.debug_buf.read() {
+            bytes.push(b);
+        }
+        let (mut at, mut frames) = (0, 0);
+        while at < bytes.len() {
+            match Message::decode(&bytes[at..]) {
+                Decoded::Frame(_, size) => at += size,
+                decoded => panic!("{:?} at {}", decoded, at),
+            }
+            frames += 1;
+        }
+        frames
+    }
+
+    let mut device = bench();
+    for _ in 0..100 {
+        tick_unread(&mut device);
+    }
+    assert!(read_frames(&mut device) < 200);
+
+    // the channel takes frames again once it has been read
+    tick_unread(&mut device);
+    assert_eq!(read_frames(&mut device), 2);
+}
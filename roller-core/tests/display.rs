@@ -0,0 +1,65 @@
+use roller_core::display::segment::POINT;
+use roller_core::display::symbol::*;
+use roller_core::display::{encode_i16_into, encode_u16_into, encode_u8_into, Display};
+
+#[test]
+fn u8_numbers_are_encoded_without_leading_zeros() {
+    let mut buf = [0xffu8; 3];
+    assert_eq!(encode_u8_into(&mut buf, 0), 1);
+    assert_eq!(buf, [ZERO, 0, 0]);
+
+    assert_eq!(encode_u8_into(&mut buf, 7), 1);
+    assert_eq!(buf, [SEVEN, 0, 0]);
+
+    assert_eq!(encode_u8_into(&mut buf, 205), 3);
+    assert_eq!(buf, [TWO, ZERO, FIVE]);
+}
+
+#[test]
+fn u16_numbers_are_encoded_without_leading_zeros() {
+    let mut buf = [0xffu8; 4];
+    assert_eq!(encode_u16_into(&mut buf, 40), 2);
+    assert_eq!(buf, [FOUR, ZERO, 0, 0]);
+
+    assert_eq!(encode_u16_into(&mut buf, 1209), 4);
+    assert_eq!(buf, [ONE, TWO, ZERO, NINE]);
+}
+
+#[test]
+fn numbers_are_aligned_to_the_right() {
+    let mut display = Display::new();
+    display.set_number(17);
+    assert_eq!(display.buffer, [0, 0, ONE, SEVEN | POINT]);
+
+    display.set_number(400);
+    assert_eq!(display.buffer, [0, FOUR, ZERO, ZERO | POINT]);
+}
+
+#[test]
+fn signed_numbers_are_encoded_with_a_sign() {
+    let mut buf = [0xffu8; 4];
+    assert_eq!(encode_i16_into(&mut buf, -12, false), 3);
+    assert_eq!(buf, [MINUS, ONE, TWO, 0]);
+
+    assert_eq!(encode_i16_into(&mut buf, 5, true), 2);
+    assert_eq!(buf, [PLUS, FIVE, 0, 0]);
+
+    assert_eq!(encode_i16_into(&mut buf, 5, false), 1);
+    assert_eq!(buf, [FIVE, 0, 0, 0]);
+
+    assert_eq!(encode_i16_into(&mut buf, 0, true), 1);
+    assert_eq!(buf, [ZERO, 0, 0, 0]);
+}
+
+#[test]
+fn negative_numbers_are_aligned_to_the_right() {
+    let mut display = Display::new();
+    display.set_number(-3);
+    assert_eq!(display.buffer, [0, 0, MINUS, THREE | POINT]);
+
+    display.set_number(-999);
+    assert_eq!(display.buffer, [MINUS, NINE, NINE, NINE | POINT]);
+
+    display.set_number(-1500);
+    assert_eq!(display.buffer, [MINUS, NINE, NINE, NINE | POINT]);
+}
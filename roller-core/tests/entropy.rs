@@ -0,0 +1,158 @@
+use roller_core::entropy::{Failure, Generator, Health, Pool};
+
+#[test]
+fn generator_is_xoshiro128starstar() {
+    let mut rng = Generator::new([1, 2, 3, 4]);
+    let outputs: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+    assert_eq!(outputs, [11520, 0, 5927040, 70819200, 2031721883, 1637235492]);
+}
+
+#[test]
+fn draws_differ_without_fresh_bits() {
+    let mut rng = Generator::new([0; 4]);
+    let mut draws: Vec<u32> = (0..100).map(|_| rng.next_u32()).collect();
+    draws.sort();
+    draws.dedup();
+    assert_eq!(draws.len(), 100);
+}
+
+#[test]
+fn lowest_bits_are_whitened() {
+    let mut pool = Pool::new();
+    // only the lowest bits count, pairs of different bits yield the first one
+    for (first, second) in [(512, 769), (767, 512), (512, 514), (513, 1023)] {
+        pool.put(0, first);
+        pool.put(0, second);
+    }
+    assert_eq!(pool.available(), 2);
+    assert_eq!(pool.take(2), Some(0b10));
+
+    // every axis is paired up on its own
+    for axis in 0..3 {
+        pool.put(axis, 1);
+    }
+    for axis in 0..3 {
+        pool.put(axis, 0);
+    }
+    assert_eq!(pool.take(3), Some(0b111));
+    assert_eq!((pool.harvested(), pool.consumed()), (5, 5));
+}
+
+#[test]
+fn whitened_bits_are_unbiased() {
+    let mut noise = 1u32;
+    let mut pool = Pool::new();
+    let (mut ones, mut bits) = (0, 0);
+    for _ in 0..100_000 {
+        noise ^= noise << 13;
+        noise ^= noise >> 17;
+        noise ^= noise << 5;
+        // the lowest bit is set 80% of the time
+        pool.put(0, 512 + (noise % 10 < 8) as u16);
+        if let Some(bit) = pool.take(1) {
+            ones += bit;
+            bits += 1;
+        }
+    }
+    // 2 * 0.8 * 0.2 of the pairs yield a bit
+    assert!((15_500..16_500).contains(&bits), "{} bits", bits);
+    assert!((ones as f64 / bits as f64 - 0.5).abs() < 0.02, "{} ones of {}", ones, bits);
+}
+
+#[test]
+fn generator_waits_for_fresh_bits() {
+    let mut pool = Pool::new();
+    let mut rng = Generator::default();
+    assert!(!rng.reseed(&mut pool));
+    assert_eq!(rng.next_u32(), Generator::default().next_u32());
+
+    for i in 0..Generator::RESEED_BITS as u16 {
+        pool.put(0, i % 2);
+        pool.put(0, i % 2 + 1);
+    }
+    let mut reseeded = Generator::default();
+    assert!(reseeded.reseed(&mut pool));
+    assert_eq!(pool.available(), 0);
+    assert_eq!(pool.consumed(), Generator::RESEED_BITS as u16);
+    assert_ne!(reseeded.next_u32(), Generator::default().next_u32());
+}
+
+#[test]
+fn full_queue_drops_bits() {
+    let mut pool = Pool::new();
+    for _ in 0..100 {
+        pool.put(0, 0);
+        pool.put(0, 1);
+    }
+    assert_eq!(pool.available(), Pool::CAPACITY);
+    assert_eq!(pool.harvested(), Pool::CAPACITY as u16);
+    assert_eq!(pool.take(32), Some(0));
+    assert_eq!(pool.take(33), None);
+}
+
+/// Feeds the samples to new health tests. Returns the failure along with the index of the sample that caused it.
+fn test_health(samples: impl Iterator<Item = u16>) -> Option<(usize, Failure)> {
+    let mut health = Health::new();
+    samples.enumerate().find_map(|(i, sample)| {
+        health.put(sample);
+        health.failure().map(|failure| (i, failure))
+    })
+}
+
+#[test]
+fn noisy_samples_pass_the_health_tests() {
+    let mut noise = 1u32;
+    let samples = (0..10_000).map(|_| {
+        noise ^= noise << 13;
+        noise ^= noise >> 17;
+        noise ^= noise << 5;
+        511 + (noise % 3) as u16
+    });
+    assert_eq!(test_health(samples), None);
+}
+
+#[test]
+fn stuck_samples_fail_the_repetition_test() {
+    let cutoff = Health::REPETITION_CUTOFF as usize;
+    // saturated
+    assert_eq!(test_health((0..1000).map(|_| 1023)), Some((cutoff - 1, Failure::Repetition)));
+    // stuck after some noise
+    let samples = (0..1000).map(|i| if i < 100 { 500 + i % 2 } else { 0 });
+    assert_eq!(test_health(samples), Some((100 + cutoff - 1, Failure::Repetition)));
+    // a run just short of the cutoff passes
+    let samples = (0..1000).map(|i| if i % cutoff as u16 == 0 { 513 } else { 512 });
+    assert_eq!(test_health(samples), None);
+}
+
+#[test]
+fn biased_samples_fail_the_proportion_test() {
+    // every 50th sample differs, too rarely for a window but often enough for the repetition test
+    let samples = (0..1000).map(|i| if i % 50 == 49 { 513 } else { 512 });
+    assert_eq!(test_health(samples), Some((Health::PROPORTION_CUTOFF as usize + 9, Failure::Proportion)));
+}
+
+#[test]
+fn unhealthy_samples_keep_failing_after_clearing() {
+    let mut health = Health::new();
+    let biased = |i: u16| if i % 50 == 49 { 513 } else { 512 };
+    (0..Health::WINDOW).for_each(|i| health.put(biased(i)));
+    assert_eq!(health.failure(), Some(Failure::Proportion));
+
+    // the verdict of a window holds until the next one is over
+    health.clear();
+    health.put(500);
+    assert_eq!(health.failure(), Some(Failure::Proportion));
+    (1..Health::WINDOW).for_each(|i| health.put(500 + i % 3));
+    health.clear();
+    health.put(500);
+    assert_eq!(health.failure(), None);
+
+    // a stuck axis fails on every sample until it changes
+    (0..Health::REPETITION_CUTOFF).for_each(|_| health.put(0));
+    health.clear();
+    health.put(0);
+    assert_eq!(health.failure(), Some(Failure::Repetition));
+    health.clear();
+    health.put(1);
+    assert_eq!(health.failure(), None);
+}
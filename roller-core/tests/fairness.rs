@@ -0,0 +1,189 @@
+//! Statistical evidence that the dice are fair: the results of every die of the quality knob, thrown on their own
+//! and as sums of several dice through the whole roll of the device, are compared with their exact distributions
+//! with the chi-square and Kolmogorov-Smirnov tests.
+//!
+//! The entropy comes from seeded generators, so the tests are reproducible. Both tests reject at the 10^-4
+//! significance level.
+
+mod common;
+
+use common::{bench, tick, Bench};
+use roller_core::device::State;
+use roller_core::entropy::Generator;
+use roller_core::hardware::Measurement;
+use roller_core::random::{generate, params_for};
+use roller_core::scales::{CUSTOM, FUDGE, QUALITY};
+
+/// The standard normal quantile of the significance level, 1 - 10^-4.
+const Z: f64 = 3.719;
+
+/// Returns the number of faces of the die selected with the quality knob in the specified zone.
+fn faces(quality: u16) -> u16 {
+    match quality {
+        FUDGE => 3,
+        CUSTOM => Bench::DEFAULT_CUSTOM_SIDES,
+        sides => sides,
+    }
+}
+
+/// Returns the name of the die selected with the quality knob in the specified zone.
+fn name(quality: u16) -> String {
+    match quality {
+        FUDGE => "dF".to_string(),
+        _ => format!("d{}", faces(quality)),
+    }
+}
+
+/// Returns the exact distribution of the sum of `quantity` dice with the specified number of faces: the
+/// probability of every sum starting with the lowest one.
+fn distribution(quantity: usize, faces: u16) -> Vec<f64> {
+    let mut p = vec![1.0];
+    for _ in 0..quantity {
+        let mut next = vec![0.0; p.len() + faces as usize - 1];
+        for (sum, q) in p.iter().enumerate() {
+            for face in 0..faces as usize {
+                next[sum + face] += q / faces as f64;
+            }
+        }
+        p = next;
+    }
+    p
+}
+
+/// Fails with a description of the die unless the observed counts of every outcome pass the chi-square test
+/// against the expected probabilities. Outcomes too rare to be tested on their own are merged with neighbours.
+fn chi_square(die: &str, counts: &[u32], p: &[f64]) {
+    let n = counts.iter().sum::<u32>() as f64;
+    // (observed, expected) counts of every bin
+    let mut bins: Vec<(f64, f64)> = Vec::new();
+    let mut open = (0.0, 0.0);
+    for (count, p) in counts.iter().zip(p) {
+        open = (open.0 + *count as f64, open.1 + p * n);
+        if open.1 >= 5.0 {
+            bins.push(open);
+            open = (0.0, 0.0);
+        }
+    }
+    if let Some(last) = bins.last_mut() {
+        *last = (last.0 + open.0, last.1 + open.1);
+    }
+    let statistic: f64 = bins.iter().map(|(o, e)| (o - e) * (o - e) / e).sum();
+
+    // the Wilson-Hilferty approximation of the critical value
+    let df = (bins.len() - 1) as f64;
+    let critical = df * (1.0 - 2.0 / (9.0 * df) + Z * (2.0 / (9.0 * df)).sqrt()).powi(3);
+    assert!(statistic <= critical, "{}: chi-square {:.1} above {:.1} with {} degrees of freedom", die, statistic, critical, df);
+}
+
+/// Fails with a description of the die unless the observed counts of every outcome pass the Kolmogorov-Smirnov
+/// test against the expected probabilities. The critical value of continuous distributions is conservative for
+/// the discrete ones.
+fn kolmogorov_smirnov(die: &str, counts: &[u32], p: &[f64]) {
+    let n = counts.iter().sum::<u32>() as f64;
+    let (mut observed, mut expected, mut statistic) = (0.0, 0.0, 0.0f64);
+    for (count, p) in counts.iter().zip(p) {
+        observed += *count as f64 / n;
+        expected += p;
+        statistic = statistic.max((observed - expected).abs());
+    }
+    let critical = (-(1e-4f64 / 2.0).ln() / (2.0 * n)).sqrt();
+    assert!(statistic <= critical, "{}: Kolmogorov-Smirnov {:.4} above {:.4}", die, statistic, critical);
+}
+
+#[test]
+fn every_die_is_fair() {
+    let mut rng = Generator::new([0x2545_f491, 0x4f6c_dd1d, 0x9e37_79b9, 0x7f4a_7c15]);
+    for zone in &QUALITY {
+        let faces = faces(zone.value);
+        let params = params_for(faces);
+        let mut counts = vec![0; faces as usize];
+        let mut thrown = 0;
+        while thrown < 1000 * faces as u32 {
+            if let Some(n) = generate(&params, (rng.next_u32() >> 16) as u16) {
+                counts[n as usize] += 1;
+                thrown += 1;
+            }
+        }
+        let p = distribution(1, faces);
+        chi_square(&name(zone.value), &counts, &p);
+        kolmogorov_smirnov(&name(zone.value), &counts, &p);
+    }
+}
+
+/// A device rolling the dice as fast as possible, with the accelerometer picking up the noise of a generator.
+struct Table {
+    device: Bench,
+    noise: Generator,
+}
+
+impl Table {
+    fn new() -> Self {
+        let mut table = Self { device: bench(), noise: Generator::new([0x8a5c_d789, 0x635d_2dff, 0x121f_d215, 0x5f89_5f6b]) };
+        for _ in 0..40 {
+            table.tick();
+        }
+        // every tick is balanced and a roll ends as soon as the dice are complete
+        table.send("threshold force 1000\r");
+        table.send("threshold balance 1\r");
+        table
+    }
+
+    fn tick(&mut self) {
+        let noise = &mut self.noise;
+        tick(&mut self.device, |m| {
+            let acc = 508 + (noise.next_u32() >> 29) as u16;
+            match m {
+                Measurement::PotQuantity => 814,
+                Measurement::PotQuality => 511,
+                Measurement::AccX | Measurement::AccY => acc,
+                Measurement::AccZ => acc + 256,
+            }
+        });
+        // the port isn't inspected, don't let it grow
+        self.device.port.outputs.clear();
+    }
+
+    fn send(&mut self, line: &str) {
+        line.bytes().for_each(|b| self.device.console_receive(b));
+        while self.device.console.read().is_some() {}
+    }
+
+    /// Rolls the preset `n` times. Returns how many times every total has come up, starting with the lowest one.
+    fn roll(&mut self, preset: &str, n: u32, lowest: i16, highest: i16) -> Vec<u32> {
+        self.send(&format!("preset {}\r", preset));
+        let mut counts = vec![0; (highest - lowest + 1) as usize];
+        for _ in 0..n {
+            self.send("roll\r");
+            while matches!(self.device.state(), State::Rolling { .. }) {
+                self.tick();
+            }
+            let total = self.device.last_roll().unwrap().total();
+            assert!((lowest..=highest).contains(&total), "{}: {} rolled", preset, total);
+            counts[(total - lowest) as usize] += 1;
+        }
+        counts
+    }
+}
+
+#[test]
+fn sums_of_every_die_are_fair() {
+    let mut table = Table::new();
+    for zone in &QUALITY {
+        let faces = faces(zone.value);
+        let p = distribution(2, faces);
+        let preset = format!("2{}", name(zone.value));
+        let (lowest, highest) = if zone.value == FUDGE { (-2, 2) } else { (2, 2 * faces as i16) };
+        let counts = table.roll(&preset, 40 * p.len() as u32, lowest, highest);
+        chi_square(&preset, &counts, &p);
+        kolmogorov_smirnov(&preset, &counts, &p);
+    }
+
+    // the sums players roll the most
+    for (preset, quantity, faces, lowest) in [("3d6", 3, 6, 3), ("4dF", 4, 3, -4), ("8d6", 8, 6, 8)] {
+        let p = distribution(quantity, faces);
+        let highest = lowest + p.len() as i16 - 1;
+        let counts = table.roll(preset, 4_000, lowest, highest);
+        chi_square(preset, &counts, &p);
+        kolmogorov_smirnov(preset, &counts, &p);
+    }
+}
@@ -0,0 +1,62 @@
+use roller_core::device::{Group, Mode, Reroll};
+use roller_core::notation::{parse, Error};
+use roller_core::scales::FUDGE;
+
+#[test]
+fn expressions_are_parsed() {
+    let program = parse("4d6kh3").unwrap();
+    assert_eq!(program.groups(), [Group { quantity: 4, sides: 6 }]);
+    assert_eq!(program.mode, Mode::KeepHighest(3));
+
+    assert_eq!(parse("2d20kl1").unwrap().mode, Mode::KeepLowest(1));
+    assert_eq!(parse("1d20+5").unwrap().modifier, 5);
+    assert!(parse("8d6!").unwrap().exploding);
+    assert_eq!(parse("10d10>=7").unwrap().mode, Mode::Successes { target: 7, botches: false });
+    assert_eq!(parse("6d10>=8f1").unwrap().mode, Mode::Successes { target: 8, botches: true });
+    assert_eq!(parse("2d6ro<2").unwrap().reroll, Reroll::Once(2));
+    assert_eq!(parse("2d6r<1").unwrap().reroll, Reroll::Always(1));
+
+    assert_eq!(parse("1d1000").unwrap().groups(), [Group { quantity: 1, sides: 1000 }]);
+
+    let program = parse("2d6+1d8-2").unwrap();
+    assert_eq!(program.groups(), [Group { quantity: 2, sides: 6 }, Group { quantity: 1, sides: 8 }]);
+    assert_eq!(program.modifier, -2);
+
+    // shorthands
+    assert_eq!(parse("d20").unwrap().groups(), [Group { quantity: 1, sides: 20 }]);
+    assert_eq!(parse("2d%").unwrap().groups(), [Group { quantity: 2, sides: 100 }]);
+    assert_eq!(parse("4dF").unwrap().groups(), [Group { quantity: 4, sides: FUDGE }]);
+    assert_eq!(parse("2d20kh").unwrap().mode, Mode::KeepHighest(1));
+}
+
+#[test]
+fn expressions_are_rendered_back() {
+    for expression in ["3d6", "1d20+5", "4d6kh3", "2d6!", "10d10>=7f1", "2d6ro<2", "2d6+1d8!r<1kl2-3", "4dF"] {
+        assert_eq!(parse(expression).unwrap().to_string(), expression);
+    }
+    assert_eq!(parse("d20").unwrap().to_string(), "1d20");
+}
+
+#[test]
+fn invalid_expressions_are_rejected() {
+    assert_eq!(parse(""), Err(Error::Syntax(0)));
+    assert_eq!(parse("3x6"), Err(Error::Syntax(0)));
+    assert_eq!(parse("1d6?"), Err(Error::Syntax(3)));
+    assert_eq!(parse("1d6+"), Err(Error::Syntax(4)));
+    assert_eq!(parse("2d6-1d8"), Err(Error::Syntax(4)));
+    assert_eq!(parse("1d20+5+5"), Err(Error::Syntax(7)));
+    assert_eq!(parse("4d6kh3kl1"), Err(Error::Syntax(6)));
+
+    assert_eq!(parse("1d1"), Err(Error::OutOfRange(2)));
+    assert_eq!(parse("1d1001"), Err(Error::OutOfRange(2)));
+    assert_eq!(parse("0d6"), Err(Error::OutOfRange(0)));
+    assert_eq!(parse("21d6"), Err(Error::OutOfRange(0)));
+    assert_eq!(parse("1d20+100"), Err(Error::OutOfRange(5)));
+    assert_eq!(parse("4d6kh0"), Err(Error::OutOfRange(5)));
+
+    assert_eq!(parse("12d6+12d6"), Err(Error::TooManyDice));
+    assert_eq!(parse("1d4+1d6+1d8+1d10+1d12"), Err(Error::TooManyGroups));
+    assert_eq!(parse("4dF+1d6"), Err(Error::MixedFudge));
+    assert_eq!(parse("4dF>=2"), Err(Error::FudgeSuccesses));
+    assert_eq!(parse("10d10>=7+2"), Err(Error::ModifiedSuccesses));
+}
@@ -0,0 +1,55 @@
+use roller_core::device::MAX_DICE;
+use roller_core::protocol::{Decoded, Message, StateId, MAX_FRAME, SYNC};
+
+fn encode(message: &Message) -> Vec<u8> {
+    let mut frame = [0; MAX_FRAME];
+    let size = message.encode(&mut frame);
+    frame[..size].to_vec()
+}
+
+#[test]
+fn messages_survive_a_round_trip() {
+    let mut dice = [0; MAX_DICE];
+    dice.fill(1000);
+    let messages = [
+        Message::Acc([0x3ff, 0, 0xa5a5]),
+        Message::Pots([894, 765]),
+        Message::State(StateId::Sleeping),
+        Message::Entropy(0xffff),
+        Message::Result { sum: 20000, modifier: 99, dice, count: MAX_DICE as u8 },
+        Message::Result { sum: 0, modifier: -99, dice: [0; MAX_DICE], count: 0 },
+    ];
+
+    for message in messages {
+        let frame = encode(&message);
+        assert_eq!(frame[0], SYNC);
+        assert_eq!(Message::decode(&frame), Decoded::Frame(message, frame.len()));
+    }
+}
+
+#[test]
+fn frames_are_laid_out_as_documented() {
+    assert_eq!(encode(&Message::Entropy(0x1234)), [SYNC, 4, 2, 0x12, 0x34, 0x7f]);
+}
+
+#[test]
+fn partial_frames_are_incomplete() {
+    let frame = encode(&Message::Acc([1, 2, 3]));
+    for size in 0..frame.len() {
+        assert_eq!(Message::decode(&frame[..size]), Decoded::Incomplete, "{} bytes", size);
+    }
+}
+
+#[test]
+fn corrupted_frames_are_invalid() {
+    let frame = encode(&Message::Pots([1, 2]));
+    for i in 0..frame.len() {
+        let mut corrupted = frame.clone();
+        corrupted[i] ^= 0x10;
+        assert_ne!(Message::decode(&corrupted), Decoded::Frame(Message::Pots([1, 2]), frame.len()), "byte {}", i);
+    }
+
+    assert_eq!(Message::decode(&[0x00, 1, 6]), Decoded::Invalid);
+    // unknown state with a valid checksum
+    assert_eq!(Message::decode(&[SYNC, 3, 1, 7, 0xbd]), Decoded::Invalid);
+}
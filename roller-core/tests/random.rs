@@ -0,0 +1,41 @@
+use roller_core::random::{generate, params_for};
+
+/// Every die up to d1024, which includes the d1000 of random tables, a few hundred larger bounds spread over
+/// the whole range and the powers of two.
+fn bounds() -> impl Iterator<Item = u16> {
+    (1..=1024).chain((1025..=u16::MAX).step_by(127)).chain((11..16).map(|k| 1 << k)).chain([u16::MAX])
+}
+
+/// Returns how many times every result below the bound is generated from all the possible entropy.
+fn counts(bound: u16) -> Vec<u32> {
+    let params = params_for(bound);
+    let mut counts = vec![0; bound as usize];
+    for entropy in 0..=u16::MAX {
+        if let Some(n) = generate(&params, entropy) {
+            assert!(n < bound, "d{}: {} generated from {}", bound, n, entropy);
+            counts[n as usize] += 1;
+        }
+    }
+    counts
+}
+
+#[test]
+fn results_are_uniform_for_every_bound() {
+    for bound in bounds() {
+        let counts = counts(bound);
+        // every result is generated from exactly the same number of entropy values
+        let expected = (1 << 16) / bound as u32;
+        assert!(counts.iter().all(|c| *c == expected), "d{} isn't uniform", bound);
+    }
+}
+
+#[test]
+fn little_entropy_is_rejected() {
+    for bound in bounds() {
+        let rejected = (1 << 16) - counts(bound).iter().sum::<u32>();
+        assert!(rejected < bound as u32, "d{}: {} rejected", bound, rejected);
+        if bound.is_power_of_two() {
+            assert_eq!(rejected, 0, "d{}", bound);
+        }
+    }
+}
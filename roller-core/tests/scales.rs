@@ -0,0 +1,59 @@
+use roller_core::scales::{detect_step, detect_zone, detect_zone_change, ADVANTAGE, CUSTOM, DISADVANTAGE, FUDGE, QUALITY, QUANTITY, SETUP};
+
+#[test]
+fn scales_cover_the_whole_adc_range() {
+    let quantity = QUANTITY.iter().map(|z| (z.start, z.end));
+    let quality = QUALITY.iter().map(|z| (z.start, z.end));
+    for scale in [quantity.collect::<Vec<_>>(), quality.collect()] {
+        assert_eq!(scale[0].0, 0);
+        assert_eq!(scale[scale.len() - 1].1, 1023);
+        for pair in scale.windows(2) {
+            assert_eq!(pair[0].1 + 1, pair[1].0);
+        }
+    }
+}
+
+#[test]
+fn zones_are_detected_by_position() {
+    assert_eq!(detect_zone(0, &QUANTITY).value, 20);
+    assert_eq!(detect_zone(907, &QUANTITY).value, 1);
+    // past 1d20
+    assert_eq!(detect_zone(953, &QUANTITY).value, ADVANTAGE);
+    assert_eq!(detect_zone(977, &QUANTITY).value, DISADVANTAGE);
+    assert_eq!(detect_zone(1023, &QUANTITY).value, SETUP);
+    assert_eq!(detect_zone(460, &QUALITY).value, 8);
+    assert_eq!(detect_zone(0, &QUALITY).value, 100);
+    // past d2
+    assert_eq!(detect_zone(883, &QUALITY).value, FUDGE);
+    assert_eq!(detect_zone(1023, &QUALITY).value, CUSTOM);
+}
+
+#[test]
+fn zone_changes_are_debounced() {
+    let current = detect_zone(460, &QUALITY);
+
+    // within the dead area past the end of the zone
+    assert!(detect_zone_change(470, 10, current, &QUALITY).is_none());
+    // beyond the dead area
+    assert_eq!(detect_zone_change(480, 10, current, &QUALITY).unwrap().value, 6);
+    // within the dead area before the start of the zone
+    assert!(detect_zone_change(365, 10, current, &QUALITY).is_none());
+    // beyond the dead area
+    assert_eq!(detect_zone_change(355, 10, current, &QUALITY).unwrap().value, 10);
+}
+
+#[test]
+fn step_changes_are_debounced() {
+    // 41 steps of ~25 positions
+    assert_eq!(detect_step(0, 41, None, 4), 0);
+    assert_eq!(detect_step(512, 41, None, 4), 20);
+    assert_eq!(detect_step(1023, 41, None, 4), 40);
+
+    // step 20 covers 499-523
+    assert_eq!(detect_step(526, 41, Some(20), 4), 20);
+    assert_eq!(detect_step(528, 41, Some(20), 4), 21);
+    assert_eq!(detect_step(496, 41, Some(20), 4), 20);
+    assert_eq!(detect_step(494, 41, Some(20), 4), 19);
+    // a far away position is picked up right away
+    assert_eq!(detect_step(0, 41, Some(20), 4), 0);
+}
@@ -0,0 +1,56 @@
+use roller_core::utils::{Agg, Ring};
+
+#[test]
+fn agg_is_empty_until_filled() {
+    let mut agg: Agg<u16, 4> = Agg::new();
+    assert_eq!(agg.avg_full::<u16>(), None);
+    assert_eq!(agg.amplitude_full(), None);
+
+    agg.put(1);
+    agg.put(2);
+    agg.put(3);
+    assert_eq!(agg.avg_full::<u16>(), None);
+    assert_eq!(agg.sum_of_first::<u16>(3), Some(6));
+    assert_eq!(agg.sum_of_first::<u16>(4), None);
+    assert_eq!(agg.sum_of_first::<u16>(5), None);
+}
+
+#[test]
+fn agg_aggregates_latest_values() {
+    let mut agg: Agg<u16, 4> = Agg::new();
+    for n in [100, 1, 10, 20, 30, 40] {
+        agg.put(n);
+    }
+
+    assert_eq!(agg.avg_full::<u16>(), Some(25));
+    assert_eq!(agg.range_of_first(4), Some((10, 40)));
+    assert_eq!(agg.amplitude_full(), Some(30));
+}
+
+#[test]
+fn agg_sums_through_wider_type() {
+    let mut agg: Agg<u8, 20> = Agg::new();
+    for _ in 0..20 {
+        agg.put(200);
+    }
+
+    assert_eq!(agg.sum_of_first::<u16>(20), Some(4000));
+}
+
+#[test]
+fn ring_counts_free_bytes() {
+    let mut ring: Ring<4> = Ring::new();
+    assert_eq!(ring.free(), 4);
+
+    ring.write(1);
+    ring.write(2);
+    ring.write(3);
+    assert_eq!(ring.free(), 1);
+    assert_eq!(ring.read(), Some(1));
+    assert_eq!(ring.free(), 2);
+
+    ring.write(4);
+    ring.write(5);
+    assert_eq!(ring.free(), 0);
+    assert!(ring.is_full());
+}
@@ -0,0 +1,84 @@
+//! Decodes the messages sent over the `debug_spi` channel into CSV or JSON lines.
+//!
+//! Usage: decode [--json] [--export] FILE
+//!
+//! FILE is a raw dump of the bytes sent by the MCU, or `-` to read it from stdin.
+//! With --export, FILE is a CSV export of a logic analyzer's SPI decoder instead.
+//!
+//! CSV output contains one line per accelerometer frame, along with the aggregations the roll
+//! detection works with. JSON output contains every message, each one tagged with its "type".
+
+use std::io::{self, Read, Write};
+use std::process::ExitCode;
+use std::{env, fs};
+
+use roller_core::protocol::Message;
+use tools::decode::{self, Aggregator, CSV_HEADER};
+use tools::trace;
+
+/// Reads the whole input file, or stdin if the path is `-`.
+fn read_input(path: &str) -> io::Result<Vec<u8>> {
+    if path == "-" {
+        let mut bytes = Vec::new();
+        io::stdin().read_to_end(&mut bytes)?;
+        return Ok(bytes);
+    }
+    fs::read(path)
+}
+
+fn main() -> ExitCode {
+    let mut json = false;
+    let mut export = false;
+    let mut path = None;
+    for arg in env::args().skip(1) {
+        match arg.as_str() {
+            "--json" => json = true,
+            "--export" => export = true,
+            _ if path.is_none() => path = Some(arg),
+            _ => path = None,
+        }
+    }
+    let Some(path) = path else {
+        eprintln!("usage: decode [--json] [--export] FILE");
+        return ExitCode::FAILURE;
+    };
+
+    let mut bytes = match read_input(&path) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("{}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    if export {
+        bytes = match trace::parse_export(&String::from_utf8_lossy(&bytes)) {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                eprintln!("{}: {}", path, e);
+                return ExitCode::FAILURE;
+            }
+        };
+    }
+
+    let mut out = io::stdout().lock();
+    if !json {
+        let _ = writeln!(out, "{}", CSV_HEADER);
+    }
+    let mut aggregator = Aggregator::default();
+    for message in trace::messages(&bytes) {
+        let line = match message {
+            Message::Acc(sample) => {
+                let row = aggregator.put(sample);
+                if json { row.json() } else { row.csv() }
+            }
+            _ if json => decode::json(&message).unwrap(),
+            _ => continue,
+        };
+        if writeln!(out, "{}", line).is_err() {
+            // the reader has gone away
+            break;
+        }
+    }
+
+    ExitCode::SUCCESS
+}
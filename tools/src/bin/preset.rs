@@ -0,0 +1,62 @@
+//! Validates an expression in the dice notation and optionally makes it the active preset of the device.
+//!
+//! Usage: preset EXPRESSION [PORT]
+//!
+//! Prints the expression the way the device reports it along with what it rolls. With PORT, e.g. /dev/ttyUSB0,
+//! sends it to the serial console of a device built with `CONSOLE=1` and prints the reply. The port has to be
+//! configured beforehand, e.g. with `stty -F /dev/ttyUSB0 9600 raw -echo`.
+
+use std::env;
+use std::fs::OpenOptions;
+use std::process::ExitCode;
+
+use roller_core::notation::{self, Error};
+use tools::preset;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (expression, port) = match args.as_slice() {
+        [expression] => (expression, None),
+        [expression, port] => (expression, Some(port)),
+        _ => {
+            eprintln!("usage: preset EXPRESSION [PORT]");
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let program = match notation::parse(expression) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("error: {}", e.message());
+            if let Error::Syntax(at) | Error::OutOfRange(at) = e {
+                // point at the offending character
+                eprintln!("  {}", expression);
+                eprintln!("  {}^", " ".repeat(at));
+            }
+            return ExitCode::FAILURE;
+        }
+    };
+    println!("{}", program);
+    for line in preset::describe(&program) {
+        println!("  {}", line);
+    }
+
+    let Some(port) = port else {
+        return ExitCode::SUCCESS;
+    };
+    let Some(line) = preset::line(&program) else {
+        eprintln!("error: the expression is too long for the console");
+        return ExitCode::FAILURE;
+    };
+    let reply = OpenOptions::new().read(true).write(true).open(port).and_then(|mut f| preset::send(&mut f, &line));
+    match reply {
+        Ok(reply) => {
+            println!("{}", reply);
+            if reply == "ok" { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+        }
+        Err(e) => {
+            eprintln!("{}: {}", port, e);
+            ExitCode::FAILURE
+        }
+    }
+}
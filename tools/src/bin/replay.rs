@@ -0,0 +1,54 @@
+//! Replays an accelerometer trace captured from the `debug_spi` stream through the roll detection
+//! and reports every state transition.
+//!
+//! Usage: replay CAPTURE [NdM]
+//!
+//! The knobs are set to NdM (1d20 by default) for the whole replay.
+
+use std::process::ExitCode;
+use std::{env, fs};
+
+use roller_core::scales::{FUDGE, QUALITY, QUANTITY};
+use tools::replay::replay;
+use tools::{pot_position, trace};
+
+/// Parses the settings in the "NdM" notation into pot positions. M can be `F` for Fudge dice.
+fn parse_dice(dice: &str) -> Option<(u16, u16)> {
+    let (quantity, quality) = dice.split_once('d')?;
+    let quality = if quality == "F" { FUDGE } else { quality.parse().ok()? };
+    Some((
+        pot_position(quantity.parse().ok()?, &QUANTITY)?,
+        pot_position(quality, &QUALITY)?,
+    ))
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().collect();
+    if args.len() < 2 || args.len() > 3 {
+        eprintln!("usage: {} CAPTURE [NdM]", args[0]);
+        return ExitCode::FAILURE;
+    }
+
+    let dice = args.get(2).map(String::as_str).unwrap_or("1d20");
+    let Some((pot_quantity, pot_quality)) = parse_dice(dice) else {
+        eprintln!("unsupported dice: {}", dice);
+        return ExitCode::FAILURE;
+    };
+
+    let bytes = match fs::read(&args[1]) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("{}: {}", args[1], e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let samples = trace::parse(&bytes);
+    println!("{} samples", samples.len());
+
+    for t in replay(&samples, pot_quantity, pot_quality) {
+        println!("{}", t);
+    }
+
+    ExitCode::SUCCESS
+}
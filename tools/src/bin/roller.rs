@@ -0,0 +1,60 @@
+//! Rolls dice on the host with the logic of the device, fed by the random number generator of the OS.
+//!
+//! Usage: roller EXPRESSION [COUNT]
+//!
+//! EXPRESSION is in the dice notation, e.g. `4d6kh3` or `2d6+1d8+3`, see `roller-core/src/notation.rs`.
+//! Prints COUNT rolls, one per line, in the format of the `last` console command.
+
+use std::env;
+use std::process::ExitCode;
+
+use roller_core::notation;
+use tools::roller;
+
+/// Hands out the entropy of the OS random number generator two bytes at a time, a buffer at a time.
+struct OsEntropy {
+    buf: [u8; 256],
+    used: usize,
+}
+
+impl OsEntropy {
+    fn new() -> Self {
+        Self { buf: [0; 256], used: 256 }
+    }
+
+    fn next(&mut self) -> u16 {
+        if self.used == self.buf.len() {
+            getrandom::getrandom(&mut self.buf).expect("the OS random number generator has failed");
+            self.used = 0;
+        }
+        self.used += 2;
+        u16::from_le_bytes([self.buf[self.used - 2], self.buf[self.used - 1]])
+    }
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = env::args().skip(1).collect();
+    let (expression, count) = match args.as_slice() {
+        [expression] => (expression, Some(1)),
+        [expression, count] => (expression, count.parse().ok()),
+        _ => (&String::new(), None),
+    };
+    let Some(count) = count else {
+        eprintln!("usage: roller EXPRESSION [COUNT]");
+        return ExitCode::FAILURE;
+    };
+
+    let program = match notation::parse(expression) {
+        Ok(program) => program,
+        Err(e) => {
+            eprintln!("{}: {}", expression, e.message());
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let mut entropy = OsEntropy::new();
+    for _ in 0..count {
+        println!("{}", roller::text(&roller::roll(&program, || entropy.next())));
+    }
+    ExitCode::SUCCESS
+}
@@ -0,0 +1,250 @@
+//! Runs the device logic in the terminal in real time, feeding it synthetic ADC values and
+//! rendering what the displays show.
+//!
+//! Keys:
+//!   Up/Down      turn the quantity knob
+//!   Left/Right   turn the quality knob
+//!   [/]          turn the quality knob slightly, e.g. through the values of the settings menu
+//!   +/-          change the modifier
+//!   S            add a side to the dice selected past d2, wrapping after 1000
+//!   H/L          keep one more of the highest/lowest dice
+//!   A            add all the dice together
+//!   C            count the dice that meet one more target, wrapping after 20
+//!   B            make 1s cancel successes or stop them from doing so
+//!   R            reroll 1s once, then 1s and 2s once, then until a higher face comes up, then never
+//!   D            show or hide the dropped dice after a roll
+//!   I            show or hide every die after a roll
+//!   N            enable or disable the animation of natural maximum and minimum faces
+//!   E            make the dice explode or stop exploding
+//!   P            add the selected dice to the pool rolled along with whatever gets selected next
+//!   X            empty the pool
+//!   Space        shake the device for a second
+//!   Q/Esc        quit
+
+use std::f64::consts::TAU;
+use std::io::{self, Write};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crossterm::event::{self, Event, KeyCode, KeyEventKind};
+use crossterm::{cursor, execute, queue, terminal};
+
+use roller_core::device::{Mode, Reroll, MAX_SIDES};
+use roller_core::hardware::Measurement;
+use tools::{bench, frequency, perceived, render, tick, Bench};
+
+const FRAME: Duration = Duration::from_millis(33);
+
+/// Accelerometer readings at rest: 1g (256 ADC steps) pulls the Z axis away from the middle of the scale.
+const REST: [u16; 3] = [512, 512, 768];
+
+/// Synthetic inputs of the device.
+struct Inputs {
+    pot_quantity: u16,
+    pot_quality: u16,
+    /// How many more ticks the device is going to be shaken for.
+    shaking_ticks: u16,
+    ticks: u64,
+    rng: u32,
+}
+
+impl Inputs {
+    /// Half-width of the knob zones on the corresponding scales.
+    const QUANTITY_STEP: i16 = 47;
+    const QUALITY_STEP: i16 = 93;
+    /// A slight turn of the quality knob, narrower than the values of the settings menu.
+    const QUALITY_FINE_STEP: i16 = 8;
+
+    /// Amplitude of the simulated shake, in ADC steps.
+    const SHAKE_AMPLITUDE: f64 = 150.0;
+    /// Amplitude of the noise always present in the accelerometer readings, in ADC steps.
+    const NOISE_AMPLITUDE: u32 = 3;
+
+    fn new() -> Self {
+        let seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().subsec_nanos();
+        Self {
+            // 3d6
+            pot_quantity: 814,
+            pot_quality: 511,
+            shaking_ticks: 0,
+            ticks: 0,
+            rng: seed | 1,
+        }
+    }
+
+    /// Turns a knob by the specified number of steps.
+    fn turn(pot: &mut u16, steps: i16) {
+        *pot = (*pot as i16 + steps).clamp(0, 1023) as u16;
+    }
+
+    /// Returns the next value of a xorshift32 generator.
+    fn noise(&mut self) -> u32 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 17;
+        self.rng ^= self.rng << 5;
+        self.rng
+    }
+
+    /// Returns a reading of the specified accelerometer axis.
+    fn acc(&mut self, axis: usize) -> u16 {
+        let mut value = REST[axis] as f64;
+        if self.shaking_ticks > 0 {
+            // every axis wobbles at its own pace
+            let period = 40.0 + 15.0 * axis as f64;
+            value += Self::SHAKE_AMPLITUDE * (TAU * self.ticks as f64 / period).sin();
+        }
+        let noise = self.noise() % (2 * Self::NOISE_AMPLITUDE + 1);
+        (value as i32 + noise as i32 - Self::NOISE_AMPLITUDE as i32).clamp(0, 1023) as u16
+    }
+
+    /// Returns the result of the specified measurement.
+    fn measure(&mut self, m: Measurement) -> u16 {
+        match m {
+            Measurement::PotQuantity => self.pot_quantity,
+            Measurement::PotQuality => self.pot_quality,
+            Measurement::AccX => self.acc(0),
+            Measurement::AccY => self.acc(1),
+            Measurement::AccZ => self.acc(2),
+        }
+    }
+
+    /// Advances the simulated time by one timer tick.
+    fn tick(&mut self, device: &mut Bench) {
+        tick(device, |m| self.measure(m));
+        self.ticks += 1;
+        self.shaking_ticks = self.shaking_ticks.saturating_sub(1);
+    }
+}
+
+/// Draws the displays and the status of the simulation.
+fn draw(out: &mut impl Write, device: &mut Bench, inputs: &Inputs) -> io::Result<()> {
+    queue!(out, cursor::MoveTo(0, 0), terminal::Clear(terminal::ClearType::All))?;
+    write!(out, "\r\n")?;
+    for line in render(&perceived(&mut device.port)) {
+        write!(out, "  {}\r\n", line)?;
+    }
+    write!(out, "\r\n")?;
+    write!(out, "state: {:<10}  tick rate: {}Hz\r\n", device.state().id().name(), frequency(device))?;
+    write!(out, "pots: quantity {:>4}, quality {:>4}\r\n", inputs.pot_quantity, inputs.pot_quality)?;
+    write!(out, "modifier: {:+}, custom dice: d{}\r\n", device.modifier(), device.custom_sides())?;
+    let mode = match device.mode() {
+        Mode::Sum => "add all the dice".to_string(),
+        Mode::KeepHighest(k) => format!("keep {} highest", k),
+        Mode::KeepLowest(k) => format!("keep {} lowest", k),
+        Mode::Successes { target, botches: false } => format!("count {}+", target),
+        Mode::Successes { target, botches: true } => format!("count {}+, 1s botch", target),
+    };
+    let reroll = match device.reroll() {
+        Reroll::Never => "never".to_string(),
+        Reroll::Once(face) => format!("{} or lower once", face),
+        Reroll::Always(face) => format!("{} or lower until higher", face),
+    };
+    write!(out, "reroll: {}\r\n", reroll)?;
+    let dropped = if device.show_dropped() { "shown" } else { "hidden" };
+    let dice = if device.show_dice() { "shown" } else { "hidden" };
+    write!(out, "mode: {}, dropped dice {}, every die {}\r\n", mode, dropped, dice)?;
+    write!(out, "exploding: {}, critical animation: {}\r\n",
+           if device.exploding() { "yes" } else { "no" },
+           if device.show_critical() { "on" } else { "off" })?;
+    let pool: String = device.pool().iter().map(|g| format!("+{}d{}", g.quantity, g.sides)).collect();
+    write!(out, "pool: {}\r\n", if pool.is_empty() { "empty" } else { &pool })?;
+    let entropy = device.entropy();
+    write!(out, "entropy: {} bits queued, {} harvested, {} consumed\r\n",
+           entropy.available(), entropy.harvested(), entropy.consumed())?;
+    write!(out, "shaking: {}\r\n", if inputs.shaking_ticks > 0 { "yes" } else { "no" })?;
+    write!(out, "\r\n")?;
+    write!(out, "Up/Down: quantity, Left/Right/[/]: quality, +/-: modifier, S: custom dice, H/L/A/C/B: mode, R: reroll, D: dropped dice\r\n")?;
+    write!(out, "I: every die, N: critical animation, E: exploding dice, P/X: add to/clear the pool, Space: shake, Q: quit\r\n")?;
+    out.flush()
+}
+
+/// Runs the simulation until the user quits.
+fn run(out: &mut impl Write) -> io::Result<()> {
+    let mut device = bench();
+    let mut inputs = Inputs::new();
+
+    let mut next_tick = Instant::now();
+    let mut next_frame = Instant::now();
+
+    loop {
+        let deadline = next_tick.min(next_frame);
+        if event::poll(deadline.saturating_duration_since(Instant::now()))? {
+            if let Event::Key(key) = event::read()? {
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    // larger quantities and qualities are closer to the start of the scales
+                    KeyCode::Up => Inputs::turn(&mut inputs.pot_quantity, -Inputs::QUANTITY_STEP),
+                    KeyCode::Down => Inputs::turn(&mut inputs.pot_quantity, Inputs::QUANTITY_STEP),
+                    KeyCode::Right => Inputs::turn(&mut inputs.pot_quality, -Inputs::QUALITY_STEP),
+                    KeyCode::Left => Inputs::turn(&mut inputs.pot_quality, Inputs::QUALITY_STEP),
+                    KeyCode::Char(']') => Inputs::turn(&mut inputs.pot_quality, -Inputs::QUALITY_FINE_STEP),
+                    KeyCode::Char('[') => Inputs::turn(&mut inputs.pot_quality, Inputs::QUALITY_FINE_STEP),
+                    KeyCode::Char('+') | KeyCode::Char('=') => device.set_modifier(device.modifier().saturating_add(1)),
+                    KeyCode::Char('-') => device.set_modifier(device.modifier().saturating_sub(1)),
+                    KeyCode::Char('s') => device.set_custom_sides(if device.custom_sides() < MAX_SIDES { device.custom_sides() + 1 } else { 2 }),
+                    KeyCode::Char('h') => device.set_mode(match device.mode() {
+                        Mode::KeepHighest(k) => Mode::KeepHighest(k % 19 + 1),
+                        _ => Mode::KeepHighest(1),
+                    }),
+                    KeyCode::Char('l') => device.set_mode(match device.mode() {
+                        Mode::KeepLowest(k) => Mode::KeepLowest(k % 19 + 1),
+                        _ => Mode::KeepLowest(1),
+                    }),
+                    KeyCode::Char('a') => device.set_mode(Mode::Sum),
+                    KeyCode::Char('c') => device.set_mode(match device.mode() {
+                        Mode::Successes { target, botches } => Mode::Successes { target: target % 20 + 1, botches },
+                        _ => Mode::Successes { target: 1, botches: false },
+                    }),
+                    KeyCode::Char('b') => if let Mode::Successes { target, botches } = device.mode() {
+                        device.set_mode(Mode::Successes { target, botches: !botches });
+                    },
+                    KeyCode::Char('r') => device.set_reroll(match device.reroll() {
+                        Reroll::Never => Reroll::Once(1),
+                        Reroll::Once(1) => Reroll::Once(2),
+                        Reroll::Once(face) => Reroll::Always(face),
+                        Reroll::Always(_) => Reroll::Never,
+                    }),
+                    KeyCode::Char('d') => device.set_show_dropped(!device.show_dropped()),
+                    KeyCode::Char('i') => device.set_show_dice(!device.show_dice()),
+                    KeyCode::Char('n') => device.set_show_critical(!device.show_critical()),
+                    KeyCode::Char('e') => device.set_exploding(!device.exploding()),
+                    KeyCode::Char('p') => if let Some(group) = device.selected() {
+                        device.add_to_pool(group);
+                    },
+                    KeyCode::Char('x') => device.clear_pool(),
+                    KeyCode::Char(' ') => inputs.shaking_ticks = Bench::NORMAL_FREQUENCY as u16,
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    _ => {}
+                }
+            }
+            continue;
+        }
+
+        let now = Instant::now();
+        if now >= next_tick {
+            inputs.tick(&mut device);
+            next_tick += Duration::from_secs(1) / frequency(&device) as u32;
+            if next_tick < now {
+                // don't try to catch up after falling behind
+                next_tick = now;
+            }
+        }
+        if now >= next_frame {
+            draw(out, &mut device, &inputs)?;
+            next_frame = now + FRAME;
+        }
+    }
+}
+
+fn main() -> io::Result<()> {
+    let mut out = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(out, terminal::EnterAlternateScreen, cursor::Hide)?;
+
+    let result = run(&mut out);
+
+    execute!(out, cursor::Show, terminal::LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
@@ -0,0 +1,115 @@
+//! Decoding of debug stream messages, including the aggregations of accelerometer samples
+//! the roll detection works with.
+
+use roller_core::device::{AccAxis, AGG_SIZE};
+use roller_core::protocol::Message;
+use roller_core::utils::Agg;
+
+use crate::trace::Sample;
+
+/// Average and amplitude of a full aggregation level.
+#[derive(Clone, Copy)]
+pub struct Level {
+    pub avg: u16,
+    pub amplitude: u16,
+}
+
+/// A decoded frame along with the aggregations of every axis right after it was measured.
+pub struct Row {
+    pub frame: usize,
+    pub sample: Sample,
+    /// Per-axis aggregations of the latest measurements, None until the level is full.
+    pub l1: [Option<Level>; 3],
+    /// Per-axis aggregations of the latest first level averages, None until the level is full.
+    pub l2: [Option<Level>; 3],
+}
+
+/// Returns the average and the amplitude of a full aggregation level.
+fn level(agg: &Agg<u16, AGG_SIZE>) -> Option<Level> {
+    Some(Level {
+        avg: agg.avg_full::<u16>()?,
+        amplitude: agg.amplitude_full()?,
+    })
+}
+
+/// Aggregates accelerometer samples the same way the device does.
+#[derive(Default)]
+pub struct Aggregator {
+    axes: [AccAxis; 3],
+    frames: usize,
+}
+
+impl Aggregator {
+    /// Adds the next sample to the aggregations, returns the sample along with them.
+    pub fn put(&mut self, sample: Sample) -> Row {
+        for (axis, m) in self.axes.iter_mut().zip(sample) {
+            axis.put(m);
+        }
+        self.frames += 1;
+
+        let axes = &self.axes;
+        Row {
+            frame: self.frames - 1,
+            sample,
+            l1: [level(&axes[0].l1), level(&axes[1].l1), level(&axes[2].l1)],
+            l2: [level(&axes[0].l2), level(&axes[1].l2), level(&axes[2].l2)],
+        }
+    }
+}
+
+/// Aggregates the samples the same way the device does.
+pub fn decode(samples: &[Sample]) -> Vec<Row> {
+    let mut aggregator = Aggregator::default();
+    samples.iter().map(|sample| aggregator.put(*sample)).collect()
+}
+
+/// Formats a message other than `Message::Acc` as a single-line JSON object.
+/// Accelerometer samples are formatted by `Row::json` along with their aggregations.
+pub fn json(message: &Message) -> Option<String> {
+    Some(match message {
+        Message::Acc(_) => return None,
+        Message::Pots([quantity, quality]) =>
+            format!("{{\"type\":\"pots\",\"quantity\":{},\"quality\":{}}}", quantity, quality),
+        Message::State(id) => format!("{{\"type\":\"state\",\"state\":\"{}\"}}", id.name()),
+        Message::Entropy(entropy) => format!("{{\"type\":\"entropy\",\"entropy\":{}}}", entropy),
+        Message::Result { sum, modifier, dice, count } => {
+            let dice: Vec<String> = dice[..*count as usize].iter().map(u16::to_string).collect();
+            format!("{{\"type\":\"result\",\"sum\":{},\"modifier\":{},\"dice\":[{}]}}", sum, modifier, dice.join(","))
+        }
+    })
+}
+
+/// Names of the CSV columns matching `Row::csv`.
+pub const CSV_HEADER: &str = "frame,x,y,z,\
+l1_avg_x,l1_amp_x,l1_avg_y,l1_amp_y,l1_avg_z,l1_amp_z,\
+l2_avg_x,l2_amp_x,l2_avg_y,l2_amp_y,l2_avg_z,l2_amp_z";
+
+impl Row {
+    /// Formats the row as a CSV line. Values of levels that aren't full yet are left empty.
+    pub fn csv(&self) -> String {
+        let mut fields = vec![self.frame.to_string()];
+        fields.extend(self.sample.iter().map(u16::to_string));
+        for level in self.l1.iter().chain(&self.l2) {
+            match level {
+                Some(l) => fields.extend([l.avg.to_string(), l.amplitude.to_string()]),
+                None => fields.extend([String::new(), String::new()]),
+            }
+        }
+        fields.join(",")
+    }
+
+    /// Formats the row as a single-line JSON object. Levels that aren't full yet are null.
+    pub fn json(&self) -> String {
+        let levels = |levels: &[Option<Level>; 3]| {
+            let axes: Vec<String> = ["x", "y", "z"].iter().zip(levels).map(|(name, level)| match level {
+                Some(l) => format!("\"{}\":{{\"avg\":{},\"amp\":{}}}", name, l.avg, l.amplitude),
+                None => format!("\"{}\":null", name),
+            }).collect();
+            format!("{{{}}}", axes.join(","))
+        };
+        format!(
+            "{{\"type\":\"acc\",\"frame\":{},\"x\":{},\"y\":{},\"z\":{},\"l1\":{},\"l2\":{}}}",
+            self.frame, self.sample[0], self.sample[1], self.sample[2], levels(&self.l1), levels(&self.l2),
+        )
+    }
+}
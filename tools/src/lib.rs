@@ -0,0 +1,123 @@
+//! Helpers shared by the host tools: running the device logic against mocked hardware
+//! and rendering what it shows.
+
+pub mod trace;
+pub mod replay;
+pub mod decode;
+pub mod preset;
+pub mod roller;
+
+use roller_core::device::Device;
+use roller_core::display::segment::*;
+use roller_core::display::{position, symbol, Buffer};
+use roller_core::hardware::{Measurement, TickRate};
+use roller_core::mock::{MockDisplay, MockSampler, MockTimer};
+use roller_core::scales::Zone;
+
+/// The device logic running against mocked hardware.
+pub type Bench = Device<MockDisplay, MockSampler, MockTimer>;
+
+/// Returns a new device running against mocked hardware.
+pub fn bench() -> Bench {
+    Device::new(MockDisplay::default(), MockSampler::default(), MockTimer::default())
+}
+
+/// Simulates a single timer tick, answering every measurement the device starts during the tick
+/// with the value returned by `measure`.
+pub fn tick(device: &mut Bench, mut measure: impl FnMut(Measurement) -> u16) {
+    device.timer_interrupt();
+    while let Some(m) = device.sampler.measuring {
+        device.adc_interrupt(measure(m));
+    }
+}
+
+/// Returns the tick frequency the device currently expects.
+pub fn frequency(device: &Bench) -> u8 {
+    match device.timer.rate {
+        TickRate::Normal => Bench::NORMAL_FREQUENCY,
+        TickRate::Sleeping => Bench::SLEEPING_FREQUENCY,
+    }
+}
+
+/// Returns the pot position in the middle of the zone mapped to the specified value.
+pub fn pot_position<V: PartialEq>(value: V, scale: &[Zone<V>]) -> Option<u16> {
+    scale.iter()
+        .find(|zone| zone.value == value)
+        .map(|zone| (zone.start + zone.end) / 2)
+}
+
+/// Collects what a human would see on the displays given the outputs driven since the last call:
+/// every display keeps glowing if it was lit at least once in the meantime.
+pub fn perceived(port: &mut MockDisplay) -> Buffer {
+    let mut buffer = [0; 4];
+    // the latest output is still being driven
+    let latest = port.current();
+    for (symbol, pos) in port.outputs.drain(..).chain(latest) {
+        for (i, bit) in position::MAP.iter().enumerate() {
+            if pos & bit != 0 {
+                buffer[i] |= symbol;
+            }
+        }
+    }
+    port.outputs.extend(latest);
+    buffer
+}
+
+/// Renders the display buffer as three lines of ASCII art.
+pub fn render(buffer: &Buffer) -> [String; 3] {
+    let mut lines = [String::new(), String::new(), String::new()];
+    let seg = |symbol: u8, s: u8, c: char| if symbol & s != 0 { c } else { ' ' };
+
+    for symbol in buffer {
+        let symbol = *symbol;
+        lines[0].push(' ');
+        lines[0].push(seg(symbol, A, '_'));
+        lines[0].push(' ');
+        lines[0].push(' ');
+
+        lines[1].push(seg(symbol, F, '|'));
+        lines[1].push(seg(symbol, G, '_'));
+        lines[1].push(seg(symbol, B, '|'));
+        lines[1].push(' ');
+
+        lines[2].push(seg(symbol, E, '|'));
+        lines[2].push(seg(symbol, D, '_'));
+        lines[2].push(seg(symbol, C, '|'));
+        lines[2].push(seg(symbol, POINT, '.'));
+    }
+
+    lines
+}
+
+/// Renders the display buffer as plain text, one character per display plus dots.
+/// Symbols that don't have a textual representation are rendered as `?`.
+pub fn text(buffer: &Buffer) -> String {
+    let mut text = String::new();
+    for s in buffer {
+        text.push(match s & !POINT {
+            0 => ' ',
+            symbol::DELTA => 'd',
+            symbol::ALPHA => 'A',
+            symbol::CHARLIE => 'C',
+            symbol::ECHO => 'E',
+            symbol::FOXTROT => 'F',
+            symbol::HOTEL => 'H',
+            symbol::LIMA => 'L',
+            symbol::NOVEMBER => 'n',
+            symbol::OSCAR => 'o',
+            symbol::PAPA => 'P',
+            symbol::ROMEO => 'r',
+            symbol::MINUS => '-',
+            symbol::PLUS => '+',
+            symbol::BARS => '≡',
+            digit => symbol::MAP.iter()
+                .position(|d| *d == digit)
+                .map(|d| char::from(b'0' + d as u8))
+                .unwrap_or('?'),
+        });
+        if s & POINT != 0 {
+            text.push('.');
+        }
+    }
+    text
+}
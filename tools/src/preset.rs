@@ -0,0 +1,66 @@
+//! Making dice expressions the active preset of the device over its serial console.
+
+use std::io::{self, Read, Write};
+
+use roller_core::console::MAX_LINE;
+use roller_core::device::{Mode, Reroll};
+use roller_core::notation::Program;
+use roller_core::scales::FUDGE;
+
+/// Returns the console line that makes the program the active preset, or None if it's too long for the console.
+pub fn line(program: &Program) -> Option<String> {
+    let line = format!("preset {}", program);
+    (line.len() <= MAX_LINE).then_some(line)
+}
+
+/// Sends the line to the console and returns its reply: `ok` or `error: ` followed by the reason.
+/// The echo of the line is skipped.
+pub fn send(port: &mut (impl Read + Write), line: &str) -> io::Result<String> {
+    port.write_all(line.as_bytes())?;
+    port.write_all(b"\r")?;
+    port.flush()?;
+
+    let mut received = Vec::new();
+    let mut byte = [0u8];
+    loop {
+        if port.read(&mut byte)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        if byte[0] != b'\n' {
+            received.push(byte[0]);
+            continue;
+        }
+        let reply = String::from_utf8_lossy(&received).trim().to_string();
+        if reply == "ok" || reply.starts_with("error: ") {
+            return Ok(reply);
+        }
+        received.clear();
+    }
+}
+
+/// Describes what the program rolls, one line per setting that differs from the defaults.
+pub fn describe(program: &Program) -> Vec<String> {
+    let dice: Vec<String> = program.groups().iter()
+        .map(|g| if g.sides == FUDGE { format!("{}dF", g.quantity) } else { format!("{}d{}", g.quantity, g.sides) })
+        .collect();
+    let mut lines = vec![format!("dice: {}", dice.join(" + "))];
+    match program.mode {
+        Mode::Sum => {}
+        Mode::KeepHighest(k) => lines.push(format!("keep: {} highest", k)),
+        Mode::KeepLowest(k) => lines.push(format!("keep: {} lowest", k)),
+        Mode::Successes { target, botches: false } => lines.push(format!("count: {}+", target)),
+        Mode::Successes { target, botches: true } => lines.push(format!("count: {}+, 1s botch", target)),
+    }
+    match program.reroll {
+        Reroll::Never => {}
+        Reroll::Once(face) => lines.push(format!("reroll: {} or lower once", face)),
+        Reroll::Always(face) => lines.push(format!("reroll: {} or lower until higher", face)),
+    }
+    if program.exploding {
+        lines.push("exploding: yes".to_string());
+    }
+    if program.modifier != 0 {
+        lines.push(format!("modifier: {:+}", program.modifier));
+    }
+    lines
+}
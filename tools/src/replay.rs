@@ -0,0 +1,64 @@
+//! Replaying of captured accelerometer traces through the roll detection.
+
+use std::fmt;
+use std::time::Duration;
+
+use roller_core::hardware::Measurement;
+
+use crate::trace::Sample;
+use crate::{bench, frequency, text, tick};
+
+/// A change of the device state observed during a replay.
+pub struct Transition {
+    /// Index of the sample that triggered the transition.
+    pub tick: usize,
+    /// Simulated time since the start of the replay.
+    pub time: Duration,
+    /// Name of the new state.
+    pub state: &'static str,
+    /// What the display buffer contains right after the transition.
+    pub display: String,
+}
+
+impl fmt::Display for Transition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:>9.3}s  tick {:>6}  {:<10}  [{}]", self.time.as_secs_f64(), self.tick, self.state, self.display)
+    }
+}
+
+/// Feeds the samples to the device one per timer tick while the pots stay at the specified
+/// positions. Returns every state transition that happened.
+pub fn replay(samples: &[Sample], pot_quantity: u16, pot_quality: u16) -> Vec<Transition> {
+    let mut device = bench();
+    let mut transitions = Vec::new();
+    let mut time = Duration::ZERO;
+    let mut state = device.state().id().name();
+
+    for (i, sample) in samples.iter().enumerate() {
+        let period = Duration::from_secs(1) / frequency(&device) as u32;
+        tick(&mut device, |m| match m {
+            Measurement::PotQuantity => pot_quantity,
+            Measurement::PotQuality => pot_quality,
+            Measurement::AccX => sample[0],
+            Measurement::AccY => sample[1],
+            Measurement::AccZ => sample[2],
+        });
+        time += period;
+
+        // the port isn't inspected, don't let it grow
+        device.port.outputs.clear();
+
+        let new_state = device.state().id().name();
+        if new_state != state {
+            state = new_state;
+            transitions.push(Transition {
+                tick: i,
+                time,
+                state,
+                display: text(&device.display().buffer),
+            });
+        }
+    }
+
+    transitions
+}
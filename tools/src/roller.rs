@@ -0,0 +1,55 @@
+//! Rolling dice on the host with the same logic the device runs.
+
+use std::fmt::Write;
+
+use roller_core::device::Roll;
+use roller_core::notation::Program;
+use roller_core::random;
+
+/// Rolls the program the way the device does, taking the entropy of every throw from `entropy`. Throws that
+/// `random::generate` rejects are skipped the same way the device skips a timer tick.
+pub fn roll(program: &Program, mut entropy: impl FnMut() -> u16) -> Roll {
+    let (mut throws, params) = program.start();
+    loop {
+        if let Some(rnd) = random::generate(&params[throws.group()], entropy()) {
+            throws.put(rnd + 1);
+        }
+        if let Some(roll) = Roll::new(&throws, program.mode, program.modifier) {
+            return roll;
+        }
+    }
+}
+
+/// Renders the roll the way the console reports the last one, e.g. `15 = 3 (1) 4 6! +2`: the total followed
+/// by the dice, the dropped ones in parentheses and the exploded ones followed by `!`, the modifier and the
+/// subtotals of mixed dice.
+pub fn text(roll: &Roll) -> String {
+    let mut text = format!("{} =", roll.total());
+    for (i, d) in roll.dice().iter().enumerate() {
+        let die = match *d {
+            _ if !roll.fudge => d.to_string(),
+            1 => "-".to_string(),
+            3 => "+".to_string(),
+            _ => "0".to_string(),
+        };
+        if roll.is_kept(i) {
+            let _ = write!(text, " {}", die);
+        } else {
+            let _ = write!(text, " ({})", die);
+        }
+        if roll.is_exploded(i) {
+            text.push('!');
+        }
+    }
+    if roll.modifier != 0 {
+        let _ = write!(text, " {:+}", roll.modifier);
+    }
+    if roll.group_count > 1 {
+        let subtotals: Vec<String> = roll.groups().iter()
+            .zip(roll.subtotals())
+            .map(|(group, subtotal)| format!("{}d{}: {}", group.quantity, group.sides, subtotal))
+            .collect();
+        let _ = write!(text, " ({})", subtotals.join(", "));
+    }
+    text
+}
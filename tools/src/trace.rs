@@ -0,0 +1,141 @@
+//! Parsing of captures of the `debug_spi` stream.
+//!
+//! Captures taken before the framed protocol are still understood: that stream consisted of frames of
+//! big-endian 16-bit words, the `0xFFFF` marker followed by the AccX, AccY and AccZ measurements.
+
+use roller_core::protocol::{Decoded, Message, MAX_FRAME};
+
+/// Accelerometer measurements of a single timer tick: x, y, z.
+pub type Sample = [u16; 3];
+
+/// Size of a legacy frame in bytes, including the marker.
+const LEGACY_FRAME_SIZE: usize = 8;
+
+/// The largest value the ADC can produce.
+const MAX_MEASUREMENT: u16 = 0x3ff;
+
+/// Extracts all complete messages from a captured byte stream, skipping anything that doesn't
+/// look like a valid frame. The stream doesn't have to start at a frame boundary.
+///
+/// A capture in which more legacy frames are found than framed messages is taken for a legacy one,
+/// its samples are returned as accelerometer messages.
+pub fn messages(bytes: &[u8]) -> Vec<Message> {
+    let messages = framed_messages(bytes);
+    let legacy = parse_legacy(bytes);
+    if legacy.len() > messages.len() {
+        return legacy.into_iter().map(Message::Acc).collect();
+    }
+    messages
+}
+
+/// Extracts all complete messages of the framed protocol from a captured byte stream.
+fn framed_messages(bytes: &[u8]) -> Vec<Message> {
+    let mut messages = Vec::new();
+    let mut i = 0;
+    while i < bytes.len() {
+        match Message::decode(&bytes[i..]) {
+            Decoded::Frame(message, size) => {
+                messages.push(message);
+                i += size;
+            }
+            // either garbage or a partial frame at the end of the capture, resynchronise on the next byte
+            Decoded::Incomplete | Decoded::Invalid => i += 1,
+        }
+    }
+    messages
+}
+
+/// Extracts all accelerometer samples from a captured byte stream.
+pub fn parse(bytes: &[u8]) -> Vec<Sample> {
+    messages(bytes)
+        .into_iter()
+        .filter_map(|m| match m {
+            Message::Acc(sample) => Some(sample),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Extracts all complete frames of the legacy stream from a captured byte stream, skipping anything that
+/// doesn't look like a frame.
+pub fn parse_legacy(bytes: &[u8]) -> Vec<Sample> {
+    let mut samples = Vec::new();
+    let mut i = 0;
+    while i + LEGACY_FRAME_SIZE <= bytes.len() {
+        match parse_legacy_frame(&bytes[i..i + LEGACY_FRAME_SIZE]) {
+            Some(sample) => {
+                samples.push(sample);
+                i += LEGACY_FRAME_SIZE;
+            }
+            // resynchronise on the next byte
+            None => i += 1,
+        }
+    }
+    samples
+}
+
+/// Parses a single legacy frame, returns None if the bytes don't form a valid frame.
+fn parse_legacy_frame(frame: &[u8]) -> Option<Sample> {
+    let word = |i: usize| u16::from_be_bytes([frame[i * 2], frame[i * 2 + 1]]);
+    if word(0) != u16::MAX {
+        return None;
+    }
+
+    let sample = [word(1), word(2), word(3)];
+    if sample.iter().any(|m| *m > MAX_MEASUREMENT) {
+        return None;
+    }
+    Some(sample)
+}
+
+/// Encodes samples the same way the legacy `debug_spi` stream did.
+pub fn encode_legacy(samples: &[Sample]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(samples.len() * LEGACY_FRAME_SIZE);
+    for sample in samples {
+        bytes.extend(u16::MAX.to_be_bytes());
+        for m in sample {
+            bytes.extend(m.to_be_bytes());
+        }
+    }
+    bytes
+}
+
+/// Encodes messages the same way the `debug_spi` stream does.
+pub fn encode(messages: &[Message]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    let mut frame = [0; MAX_FRAME];
+    for message in messages {
+        let size = message.encode(&mut frame);
+        bytes.extend(&frame[..size]);
+    }
+    bytes
+}
+
+/// Extracts the bytes sent by the MCU from a CSV export of a logic analyzer's SPI decoder.
+///
+/// The first line must be a header naming the columns; bytes are taken from the "MOSI" column
+/// and can be written either in hex (`0x3F`) or in decimal.
+pub fn parse_export(text: &str) -> Result<Vec<u8>, String> {
+    let mut lines = text.lines();
+    let header = lines.next().ok_or("empty export")?;
+    let column = header.split(',')
+        .position(|name| name.trim().trim_matches('"').eq_ignore_ascii_case("mosi"))
+        .ok_or("no MOSI column in the header")?;
+
+    let mut bytes = Vec::new();
+    for (n, line) in lines.enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let value = line.split(',')
+            .nth(column)
+            .map(|v| v.trim().trim_matches('"'))
+            .ok_or_else(|| format!("line {}: no MOSI value", n + 2))?;
+        let byte = match value.strip_prefix("0x").or_else(|| value.strip_prefix("0X")) {
+            Some(hex) => u8::from_str_radix(hex, 16),
+            None => value.parse(),
+        };
+        bytes.push(byte.map_err(|_| format!("line {}: invalid byte {:?}", n + 2, value))?);
+    }
+    Ok(bytes)
+}
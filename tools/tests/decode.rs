@@ -0,0 +1,57 @@
+use roller_core::device::MAX_DICE;
+use roller_core::protocol::{Message, StateId};
+use tools::decode::{self, decode};
+use tools::trace;
+
+#[test]
+fn logic_analyzer_exports_are_parsed() {
+    let export = "Time [s],Packet ID,MOSI,MISO\n\
+        0.1,0,0xFF,0x00\n\
+        0.2,0,0xFF,0x00\n\
+        0.3,0,0x02,0x00\n\
+        0.4,0,0x00,0x00\n\
+        0.5,0,1,0x00\n\
+        0.6,0,255,0x00\n";
+    assert_eq!(trace::parse_export(export), Ok(vec![0xff, 0xff, 0x02, 0x00, 0x01, 0xff]));
+
+    assert!(trace::parse_export("Time [s],MISO\n0.1,0x00\n").is_err());
+    assert!(trace::parse_export("MOSI\n0xZZ\n").is_err());
+}
+
+#[test]
+fn levels_are_reported_once_full() {
+    let samples: Vec<_> = (0..31).map(|i| [500 + i, 512, 768]).collect();
+    let rows = decode(&samples);
+
+    assert!(rows[14].l1[0].is_none());
+    let l1 = rows[15].l1[0].unwrap();
+    assert_eq!((l1.avg, l1.amplitude), (507, 15));
+    assert!(rows[15].l2[0].is_none());
+
+    assert!(rows[29].l2[0].is_none());
+    let l2 = rows[30].l2[0].unwrap();
+    // averages of 500..=515, 501..=516, ..., 515..=530
+    assert_eq!((l2.avg, l2.amplitude), (514, 15));
+}
+
+#[test]
+fn rows_are_formatted() {
+    let rows = decode(&[[1, 2, 3]]);
+    assert_eq!(rows[0].csv(), "0,1,2,3,,,,,,,,,,,,");
+    assert_eq!(rows[0].json(), r#"{"type":"acc","frame":0,"x":1,"y":2,"z":3,"l1":{"x":null,"y":null,"z":null},"l2":{"x":null,"y":null,"z":null}}"#);
+}
+
+#[test]
+fn messages_are_formatted() {
+    let mut dice = [0; MAX_DICE];
+    dice[..3].copy_from_slice(&[6, 1, 4]);
+
+    assert_eq!(decode::json(&Message::Acc([1, 2, 3])), None);
+    assert_eq!(decode::json(&Message::Pots([894, 765])).unwrap(), r#"{"type":"pots","quantity":894,"quality":765}"#);
+    assert_eq!(decode::json(&Message::State(StateId::Sleeping)).unwrap(), r#"{"type":"state","state":"Sleeping"}"#);
+    assert_eq!(decode::json(&Message::Entropy(1234)).unwrap(), r#"{"type":"entropy","entropy":1234}"#);
+    assert_eq!(
+        decode::json(&Message::Result { sum: 11, modifier: -2, dice, count: 3 }).unwrap(),
+        r#"{"type":"result","sum":11,"modifier":-2,"dice":[6,1,4]}"#,
+    );
+}
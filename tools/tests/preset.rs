@@ -0,0 +1,73 @@
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+
+use roller_core::device::Mode;
+use roller_core::notation::parse;
+use tools::{bench, preset, tick, Bench};
+
+/// The serial console of a device resting on the table with the knobs set to 3d6.
+struct Port {
+    device: Bench,
+    received: VecDeque<u8>,
+}
+
+impl Port {
+    fn new() -> Self {
+        let mut device = bench();
+        for _ in 0..40 {
+            tick(&mut device, |m| [814, 511, 512, 512, 768][m as usize]);
+        }
+        Self { device, received: VecDeque::new() }
+    }
+}
+
+impl Write for Port {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for b in buf {
+            self.device.console_receive(*b);
+            while let Some(b) = self.device.console.read() {
+                self.received.push_back(b);
+            }
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for Port {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.received.read(buf)
+    }
+}
+
+#[test]
+fn presets_are_sent_to_the_console() {
+    let mut port = Port::new();
+
+    let line = preset::line(&parse("4d6kh3+1").unwrap()).unwrap();
+    assert_eq!(line, "preset 4d6kh3+1");
+    assert_eq!(preset::send(&mut port, &line).unwrap(), "ok");
+    assert_eq!(port.device.mode(), Mode::KeepHighest(3));
+    assert_eq!(port.device.modifier(), 1);
+
+    assert_eq!(preset::send(&mut port, "preset 4d6kh").unwrap(), "ok");
+    assert_eq!(preset::send(&mut port, "preset 4d1").unwrap(), "error: invalid argument");
+
+    // the console can't take more than a line
+    assert_eq!(preset::line(&parse("1d100+1d100+1d100+1d100!ro<99kh3+99").unwrap()), None);
+}
+
+#[test]
+fn presets_are_described() {
+    let program = parse("2d6+1d8!r<1kl2-3").unwrap();
+    assert_eq!(preset::describe(&program), [
+        "dice: 2d6 + 1d8",
+        "keep: 2 lowest",
+        "reroll: 1 or lower until higher",
+        "exploding: yes",
+        "modifier: -3",
+    ]);
+}
@@ -0,0 +1,122 @@
+use roller_core::protocol::{Message, StateId};
+use roller_core::scales::{QUALITY, QUANTITY};
+use tools::pot_position;
+use tools::replay::replay;
+use tools::trace::{self, Sample};
+
+const REST: Sample = [512, 512, 768];
+
+/// Returns the noise the readings of a healthy accelerometer always have.
+fn noise(i: usize) -> u16 {
+    (i % 3) as u16
+}
+
+/// Returns a trace of the device resting on the table.
+fn rest(ticks: usize) -> Vec<Sample> {
+    (0..ticks).map(|i| REST.map(|value| value - 1 + noise(i))).collect()
+}
+
+/// Returns a trace of the device being shaken along the X axis.
+fn shake(ticks: usize) -> Vec<Sample> {
+    (0..ticks)
+        .map(|i| [if i / 16 % 2 == 0 { 300 } else { 700 }, 511 + noise(i), 767 + noise(i)])
+        .collect()
+}
+
+fn states(samples: &[Sample]) -> Vec<&'static str> {
+    replay(samples, 814, 511).iter().map(|t| t.state).collect()
+}
+
+#[test]
+fn resting_device_falls_asleep() {
+    assert_eq!(states(&rest(200 * 31)), ["Sleeping"]);
+}
+
+#[test]
+fn shake_is_detected_as_a_roll() {
+    let samples = [rest(100), shake(200), rest(300)].concat();
+    let transitions = replay(&samples, 814, 511);
+
+    let states: Vec<_> = transitions.iter().map(|t| t.state).collect();
+    assert_eq!(states, ["Rolling", "Displaying"]);
+    assert!(transitions[1].display.ends_with('.'));
+}
+
+#[test]
+fn gentle_bump_is_ignored() {
+    let bump: Vec<Sample> = (0..20).map(|_| [560, 512, 768]).collect();
+    let samples = [rest(100), bump, rest(300)].concat();
+    assert!(states(&samples).is_empty());
+}
+
+#[test]
+fn captures_are_parsed_from_any_offset() {
+    // the first sample contains the sync byte
+    let samples = vec![[0xa5a5, 0, 0x1ff], REST, [1, 2, 3]];
+    let messages: Vec<_> = samples.iter().map(|s| Message::Acc(*s)).collect();
+    let bytes = trace::encode(&messages);
+    let frame = bytes.len() / 3;
+
+    assert_eq!(trace::parse(&bytes), samples);
+    // a partial frame in the beginning is skipped
+    assert_eq!(trace::parse(&bytes[3..]), &samples[1..]);
+    // a partial frame in the end is ignored
+    assert_eq!(trace::parse(&bytes[..bytes.len() - 1]), &samples[..2]);
+    // a corrupted frame is skipped
+    let mut corrupted = bytes.clone();
+    corrupted[frame + 4] ^= 1;
+    assert_eq!(trace::parse(&corrupted), [samples[0], samples[2]]);
+}
+
+#[test]
+fn other_messages_are_skipped() {
+    let messages = [
+        Message::State(StateId::Rolling),
+        Message::Acc(REST),
+        Message::Entropy(0xa5),
+        Message::Pots([894, 765]),
+    ];
+    let bytes = [&[0xa5, 0x00][..], &trace::encode(&messages)].concat();
+
+    assert_eq!(trace::messages(&bytes), messages);
+    assert_eq!(trace::parse(&bytes), [REST]);
+}
+
+#[test]
+fn legacy_captures_are_parsed() {
+    let samples = vec![[0x3ff, 0, 0x1ff], REST, [1, 2, 3]];
+    let bytes = trace::encode_legacy(&samples);
+
+    assert_eq!(trace::parse(&bytes), samples);
+    assert_eq!(trace::messages(&bytes), samples.iter().map(|s| Message::Acc(*s)).collect::<Vec<_>>());
+    // a partial frame in the beginning is skipped
+    assert_eq!(trace::parse(&bytes[3..]), &samples[1..]);
+    // framed captures aren't mistaken for legacy ones
+    let framed: Vec<_> = samples.iter().map(|s| Message::Acc(*s)).collect();
+    assert!(trace::parse_legacy(&trace::encode(&framed)).is_empty());
+}
+
+/// Replays every capture in `tests/traces` and compares the transitions with the ones listed
+/// in the accompanying `.expected` file, one "tick state" pair per line. The knobs are set to 1d20.
+#[test]
+fn corpus_transitions_are_unchanged() {
+    let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/tests/traces");
+    for entry in std::fs::read_dir(dir).unwrap() {
+        let path = entry.unwrap().path();
+        if path.extension() != Some("bin".as_ref()) {
+            continue;
+        }
+
+        let samples = trace::parse(&std::fs::read(&path).unwrap());
+        let pot_quantity = pot_position(1, &QUANTITY).unwrap();
+        let pot_quality = pot_position(20, &QUALITY).unwrap();
+        let actual: Vec<String> = replay(&samples, pot_quantity, pot_quality)
+            .iter()
+            .map(|t| format!("{} {}", t.tick, t.state))
+            .collect();
+
+        let expected = std::fs::read_to_string(path.with_extension("expected")).unwrap();
+        let expected: Vec<&str> = expected.lines().collect();
+        assert_eq!(actual, expected, "{}", path.display());
+    }
+}
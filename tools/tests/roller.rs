@@ -0,0 +1,112 @@
+use roller_core::device::{Roll, State};
+use roller_core::entropy::{Generator, Pool};
+use roller_core::hardware::Measurement;
+use roller_core::notation::parse;
+use tools::{bench, roller, tick, Bench};
+
+/// A xorshift32 generator of the accelerometer noise.
+struct Xorshift(u32);
+
+impl Xorshift {
+    fn next(&mut self) -> u16 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        self.0 as u16
+    }
+}
+
+/// A device on the table whose accelerometer picks up noise, along with a copy of its entropy pool and
+/// generator that records the entropy of every throw.
+struct Rig {
+    device: Bench,
+    pool: Pool,
+    rng: Generator,
+    noise: Xorshift,
+}
+
+impl Rig {
+    fn new() -> Self {
+        let mut rig = Self { device: bench(), pool: Pool::new(), rng: Generator::new([0; 4]), noise: Xorshift(1) };
+        for _ in 0..40 {
+            rig.tick();
+        }
+        // every tick is balanced and a roll ends as soon as the dice are complete
+        for line in ["threshold force 1000\r", "threshold balance 1\r"] {
+            line.bytes().for_each(|b| rig.device.console_receive(b));
+        }
+        rig
+    }
+
+    /// Simulates a timer tick. Returns the entropy of the throw made during the tick, if any.
+    fn tick(&mut self) -> Option<u16> {
+        // the same number the device draws during the tick
+        let drawing = matches!(self.device.state(), State::Rolling { throws, balanced_ticks, .. }
+            if !throws.is_complete() || *balanced_ticks == 0);
+        let draw = drawing.then(|| {
+            self.rng.reseed(&mut self.pool).then(|| (self.rng.next_u32() >> 16) as u16)
+        }).flatten();
+        let x = 510 + self.noise.next() % 5;
+        let y = 510 + self.noise.next() % 5;
+        let z = 766 + self.noise.next() % 5;
+        for (axis, result) in [x, y, z].into_iter().enumerate() {
+            self.pool.put(axis, result);
+        }
+        tick(&mut self.device, |m| match m {
+            Measurement::PotQuantity => 814,
+            Measurement::PotQuality => 511,
+            Measurement::AccX => x,
+            Measurement::AccY => y,
+            Measurement::AccZ => z,
+        });
+        draw
+    }
+
+    /// Rolls the preset on the device. Returns the roll along with the entropy of its throws.
+    fn roll(&mut self) -> (Roll, Vec<u16>) {
+        self.tick();
+        b"roll\r".iter().for_each(|b| self.device.console_receive(*b));
+        let mut entropy = Vec::new();
+        while matches!(self.device.state(), State::Rolling { .. }) {
+            entropy.extend(self.tick());
+        }
+        // the reply to the command
+        while self.device.console.read().is_some() {}
+        (*self.device.last_roll().unwrap(), entropy)
+    }
+}
+
+#[test]
+fn rolls_match_the_device_for_every_mode() {
+    let expressions = [
+        "3d6", "1d20+5", "4d6kh3", "2d20kl1", "8d6!", "10d10>=7", "6d10>=8f1", "2d6ro<2", "3d6r<2", "4dF-1",
+        "2d6+1d8", "2d100", "3d7", "1d1000", "1d4+1d6+1d12!kh2",
+    ];
+    let mut rig = Rig::new();
+    for expression in expressions {
+        let program = parse(expression).unwrap();
+        assert!(rig.device.load(&program));
+        for i in 0..200 {
+            let (device, entropy) = rig.roll();
+            let mut entropy = entropy.into_iter();
+            let host = roller::roll(&program, || entropy.next().unwrap());
+            assert_eq!(host, device, "{} roll {}", expression, i);
+        }
+    }
+}
+
+#[test]
+fn rolls_are_rendered_like_the_console_does() {
+    let mut rig = Rig::new();
+    let program = parse("2d6+1d8kh2+3").unwrap();
+    rig.device.load(&program);
+    let (roll, _) = rig.roll();
+
+    let mut reply = Vec::new();
+    b"last\r".iter().for_each(|b| rig.device.console_receive(*b));
+    while let Some(b) = rig.device.console.read() {
+        reply.push(b);
+    }
+    let reply = String::from_utf8(reply).unwrap();
+    assert_eq!(reply.lines().nth(1).unwrap(), roller::text(&roll));
+}